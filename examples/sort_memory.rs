@@ -0,0 +1,50 @@
+//! Compares the heap use of sorting keys case insensitively with
+//! `to_lowercase` against `sort_by_name`. Run with
+//! `cargo run --release --example sort_memory`.
+
+use jsonsrt::{sort::unquote, Node, SortOptions};
+
+#[global_allocator]
+static ALLOC: dhat::Alloc = dhat::Alloc;
+
+fn main() {
+  let mut node = Node::Object(
+    (0..50_000)
+      .map(|i| {
+        (
+          format!("\"Key{:05}\"", i * 7919 % 50_000).into(),
+          Node::Value("1".into()),
+        )
+      })
+      .collect(),
+  );
+  let Node::Object(xs) = &node else {
+    unreachable!()
+  };
+  let mut entries = xs.clone();
+
+  let profiler = dhat::Profiler::builder().testing().build();
+  entries.sort_by(|a, b| {
+    unquote(&a.0)
+      .to_lowercase()
+      .cmp(&unquote(&b.0).to_lowercase())
+  });
+  let stats = dhat::HeapStats::get();
+  println!(
+    "to_lowercase: {} allocations, peak {} bytes",
+    stats.total_blocks, stats.max_bytes
+  );
+  drop(profiler);
+
+  let profiler = dhat::Profiler::builder().testing().build();
+  node.sort_by_name(&SortOptions {
+    case_insensitive: true,
+    ..Default::default()
+  });
+  let stats = dhat::HeapStats::get();
+  println!(
+    "sort_by_name: {} allocations, peak {} bytes",
+    stats.total_blocks, stats.max_bytes
+  );
+  drop(profiler);
+}
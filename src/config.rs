@@ -0,0 +1,69 @@
+use std::{fs, path::Path};
+
+/// Reads the `json_key_order` property of the `[*.json]` or `[*]` section
+/// of an `.editorconfig` file, a comma separated list of keys to sort first.
+/// A later section overrides an earlier one, as in EditorConfig.
+pub fn load_sort_order_from_editorconfig(path: &Path) -> Option<Vec<String>> {
+  let content = fs::read_to_string(path).ok()?;
+  let mut in_json_section = false;
+  let mut order = None;
+  for line in content.lines().map(str::trim) {
+    if line.is_empty() || line.starts_with(['#', ';']) {
+      continue;
+    }
+    if let Some(section) = line.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+      in_json_section = matches!(section.trim(), "*.json" | "*");
+      continue;
+    }
+    if let (true, Some((key, value))) = (in_json_section, line.split_once('=')) {
+      if key.trim() == "json_key_order" {
+        order = Some(
+          value
+            .split(',')
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(str::to_owned)
+            .collect(),
+        );
+      }
+    }
+  }
+  order
+}
+
+#[cfg(test)]
+mod tests {
+  use super::load_sort_order_from_editorconfig;
+  use std::{fs, io, path::Path};
+  use tempfile::NamedTempFile;
+
+  #[test]
+  fn load_sort_order() -> io::Result<()> {
+    let tests = vec![
+      ("", None),
+      ("json_key_order = a", None),
+      ("[*.md]\njson_key_order = a", None),
+      (
+        "root = true\n\n[*.json]\n# comment\nindent_size = 2\njson_key_order = name, version ,,id\n",
+        Some(vec!["name", "version", "id"]),
+      ),
+      ("[*]\njson_key_order = a\n[*.json]\njson_key_order = b", Some(vec!["b"])),
+      ("[*.json]\njson_key_order =", Some(vec![])),
+    ];
+    for (content, expected) in tests {
+      let file = NamedTempFile::new()?;
+      fs::write(file.path(), content)?;
+      assert_eq!(
+        load_sort_order_from_editorconfig(file.path()),
+        expected.map(|x| x.into_iter().map(str::to_owned).collect()),
+        "content: {}",
+        content
+      );
+    }
+    assert_eq!(
+      load_sort_order_from_editorconfig(Path::new("/nonexistent")),
+      None
+    );
+    Ok(())
+  }
+}
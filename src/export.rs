@@ -0,0 +1,720 @@
+use crate::{
+  node::Node::{self, Array, Object, Value},
+  sort::{unescape_value, unquote},
+};
+#[cfg(feature = "xml")]
+use quick_xml::{
+  events::{BytesEnd, BytesStart, BytesText, Event},
+  Writer,
+};
+#[cfg(feature = "msgpack")]
+use serde::{
+  de::{self, MapAccess, SeqAccess, Visitor},
+  ser::{SerializeMap, SerializeSeq},
+  Deserialize, Deserializer, Serialize, Serializer,
+};
+#[cfg(feature = "xml")]
+use std::io;
+use std::{borrow::Cow, env, error::Error, fmt};
+
+#[derive(Debug, PartialEq)]
+pub enum TemplateError {
+  UnknownKey(String),
+  NotAValue(String),
+}
+
+impl fmt::Display for TemplateError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      TemplateError::UnknownKey(key) => write!(f, "unknown template key: {}", key),
+      TemplateError::NotAValue(key) => write!(f, "template key is not a value: {}", key),
+    }
+  }
+}
+
+impl Error for TemplateError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvOptions {
+  pub delimiter: char,
+  pub quote: char,
+  /// Text written for `null` values.
+  pub null_value: String,
+  /// Write a first row with the column names.
+  pub header: bool,
+}
+
+impl Default for CsvOptions {
+  fn default() -> Self {
+    CsvOptions {
+      delimiter: ',',
+      quote: '"',
+      null_value: String::new(),
+      header: true,
+    }
+  }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CsvError {
+  NotAnArray,
+  NotAnObject(usize),
+  NestedValue(usize, String),
+}
+
+impl fmt::Display for CsvError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      CsvError::NotAnArray => write!(f, "csv input must be an array of objects"),
+      CsvError::NotAnObject(i) => write!(f, "csv row {} is not an object", i),
+      CsvError::NestedValue(i, key) => {
+        write!(f, "csv row {} has a nested value for key: {}", i, key)
+      }
+    }
+  }
+}
+
+impl Error for CsvError {}
+
+#[derive(Debug, PartialEq)]
+pub enum DotenvError {
+  NotAnObject,
+  TooDeep(String),
+}
+
+impl fmt::Display for DotenvError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      DotenvError::NotAnObject => write!(f, "dotenv input must be an object"),
+      DotenvError::TooDeep(key) => write!(f, "dotenv value is nested too deep: {}", key),
+    }
+  }
+}
+
+impl Error for DotenvError {}
+
+#[cfg(feature = "xml")]
+#[derive(Debug)]
+pub enum XmlError {
+  InvalidName(String),
+  Io(io::Error),
+}
+
+#[cfg(feature = "xml")]
+impl fmt::Display for XmlError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      XmlError::InvalidName(name) => write!(f, "invalid xml element name: {}", name),
+      XmlError::Io(e) => e.fmt(f),
+    }
+  }
+}
+
+#[cfg(feature = "xml")]
+impl Error for XmlError {}
+
+#[cfg(feature = "xml")]
+impl From<io::Error> for XmlError {
+  fn from(e: io::Error) -> Self {
+    XmlError::Io(e)
+  }
+}
+
+#[cfg(feature = "msgpack")]
+#[derive(Debug)]
+pub struct DecodeError(rmp_serde::decode::Error);
+
+#[cfg(feature = "msgpack")]
+impl fmt::Display for DecodeError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "invalid msgpack input: {}", self.0)
+  }
+}
+
+#[cfg(feature = "msgpack")]
+impl Error for DecodeError {}
+
+impl Node<'_> {
+  /// Replaces `{{KEY}}` placeholders in `template` with the unquoted and
+  /// unescaped values of this object. Nested keys are addressed with dot notation, e.g.
+  /// `{{user.name}}`. Arrays and objects cannot be substituted.
+  pub fn to_template_string(&self, template: &str) -> Result<String, TemplateError> {
+    let mut buf = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+      let Some(len) = rest[start + 2..].find("}}") else {
+        break;
+      };
+      buf.push_str(&rest[..start]);
+      buf.push_str(&template_value(
+        self,
+        rest[start + 2..start + 2 + len].trim(),
+      )?);
+      rest = &rest[start + 2 + len + 2..];
+    }
+    buf.push_str(rest);
+    Ok(buf)
+  }
+}
+
+impl Node<'_> {
+  /// Converts an array of objects to CSV with one row per object. The
+  /// columns are the keys of all objects in the order they first appear, and
+  /// missing keys are left empty.
+  pub fn to_csv_with_options(&self, options: &CsvOptions) -> Result<String, CsvError> {
+    let Array(rows) = self else {
+      return Err(CsvError::NotAnArray);
+    };
+
+    let mut columns: Vec<&str> = vec![];
+    for (i, row) in rows.iter().enumerate() {
+      let Object(xs) = row else {
+        return Err(CsvError::NotAnObject(i));
+      };
+      for (key, _) in xs {
+        if !columns.contains(&key.as_ref()) {
+          columns.push(key);
+        }
+      }
+    }
+
+    let row = |cells: Vec<String>| {
+      cells
+        .iter()
+        .map(|x| csv_cell(x, options))
+        .collect::<Vec<_>>()
+        .join(&options.delimiter.to_string())
+    };
+
+    let mut lines = vec![];
+    if options.header && !columns.is_empty() {
+      lines.push(row(
+        columns.iter().map(|x| unescape_value(unquote(x))).collect(),
+      ));
+    }
+
+    for (i, xs) in rows.iter().enumerate() {
+      let Object(xs) = xs else { unreachable!() };
+      let mut cells = vec![];
+      for column in &columns {
+        cells.push(match xs.iter().find(|(key, _)| key == column) {
+          None => String::new(),
+          Some((_, Value(x))) if x == "null" => options.null_value.clone(),
+          Some((_, Value(x))) => unescape_value(unquote(x)),
+          Some((key, _)) => return Err(CsvError::NestedValue(i, unquote(key).to_owned())),
+        });
+      }
+      lines.push(row(cells));
+    }
+
+    Ok(lines.join("\n"))
+  }
+}
+
+impl Node<'_> {
+  /// Converts an object to `.env` lines of `NAME=value`. Names are the
+  /// upper-cased keys prefixed with `prefix`, with nested objects one level
+  /// deep joined by `_`, so `{"db":{"host":"x"}}` gives `DB_HOST="x"`.
+  /// Strings stay quoted and `null` becomes an empty value.
+  pub fn to_dotenv_string(&self, prefix: &str) -> Result<String, DotenvError> {
+    let Object(xs) = self else {
+      return Err(DotenvError::NotAnObject);
+    };
+
+    let mut lines = vec![];
+    for (key, x) in xs {
+      match x {
+        Object(ys) => {
+          for (k, y) in ys {
+            let Value(y) = y else {
+              return Err(DotenvError::TooDeep(format!(
+                "{}.{}",
+                unquote(key),
+                unquote(k)
+              )));
+            };
+            lines.push(dotenv_line(
+              &format!("{}{}_{}", prefix, unquote(key), unquote(k)),
+              y,
+            ));
+          }
+        }
+        Value(x) => lines.push(dotenv_line(&format!("{}{}", prefix, unquote(key)), x)),
+        Array(_) => return Err(DotenvError::TooDeep(unquote(key).to_owned())),
+      }
+    }
+    Ok(lines.join("\n"))
+  }
+}
+
+impl Node<'_> {
+  /// Builds an object from the environment variables named `<prefix>_*`,
+  /// keyed by the rest of their names in lower case, with string values.
+  /// The inverse of `to_dotenv_string` for flat objects. Variables whose
+  /// names or values are not valid UTF-8 are skipped.
+  pub fn from_env_vars(prefix: &str) -> Node<'static> {
+    Node::from_vars(
+      prefix,
+      env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?))),
+    )
+  }
+
+  /// Like `from_env_vars`, but reads the given name and value pairs instead
+  /// of the environment.
+  pub fn from_vars(
+    prefix: &str,
+    vars: impl IntoIterator<Item = (String, String)>,
+  ) -> Node<'static> {
+    let prefix = format!("{}_", prefix);
+    let mut vars = vars
+      .into_iter()
+      .filter_map(|(name, value)| Some((name.strip_prefix(&prefix)?.to_lowercase(), value)))
+      .collect::<Vec<_>>();
+    vars.sort();
+    Object(
+      vars
+        .into_iter()
+        .map(|(key, value)| (quote_value(&key).into(), Value(quote_value(&value).into())))
+        .collect(),
+    )
+  }
+}
+
+fn dotenv_line(name: &str, value: &str) -> String {
+  let name = name
+    .chars()
+    .map(|x| match x.is_ascii_alphanumeric() {
+      true => x.to_ascii_uppercase(),
+      false => '_',
+    })
+    .collect::<String>();
+  match value {
+    "null" => format!("{}=", name),
+    _ => format!("{}={}", name, value),
+  }
+}
+
+fn csv_cell(x: &str, options: &CsvOptions) -> String {
+  if x.contains([options.delimiter, options.quote, '\n', '\r']) {
+    let quote = options.quote.to_string();
+    format!("{}{}{}", quote, x.replace(&quote, &quote.repeat(2)), quote)
+  } else {
+    x.to_owned()
+  }
+}
+
+#[cfg(feature = "xml")]
+impl Node<'_> {
+  /// Converts to XML under a `<root>` element, see `to_xml_with_root`.
+  pub fn to_xml(&self) -> Result<String, XmlError> {
+    self.to_xml_with_root("root")
+  }
+
+  /// Converts to XML under an element named `root`. Object entries become
+  /// child elements named after their keys, array elements become repeated
+  /// `<item>` elements, and values become text, with `null` as an empty
+  /// element.
+  pub fn to_xml_with_root(&self, root: &str) -> Result<String, XmlError> {
+    let mut writer = Writer::new(Vec::new());
+    write_xml(self, root, &mut writer)?;
+    Ok(String::from_utf8(writer.into_inner()).expect("xml output is utf-8"))
+  }
+}
+
+#[cfg(feature = "xml")]
+fn write_xml(node: &Node, name: &str, writer: &mut Writer<Vec<u8>>) -> Result<(), XmlError> {
+  if !is_xml_name(name) {
+    return Err(XmlError::InvalidName(name.to_owned()));
+  }
+
+  if let Value(x) = node {
+    if *x == "null" {
+      writer.write_event(Event::Empty(BytesStart::new(name)))?;
+      return Ok(());
+    }
+  }
+
+  writer.write_event(Event::Start(BytesStart::new(name)))?;
+  match node {
+    Value(x) => writer.write_event(Event::Text(BytesText::new(&unescape_value(unquote(x)))))?,
+    Array(xs) => {
+      for x in xs {
+        write_xml(x, "item", writer)?;
+      }
+    }
+    Object(xs) => {
+      for (key, x) in xs {
+        write_xml(x, &unescape_value(unquote(key)), writer)?;
+      }
+    }
+  }
+  writer.write_event(Event::End(BytesEnd::new(name)))?;
+  Ok(())
+}
+
+#[cfg(feature = "xml")]
+fn is_xml_name(name: &str) -> bool {
+  let mut chars = name.chars();
+  chars.next().is_some_and(|x| x.is_alphabetic() || x == '_')
+    && chars.all(|x| x.is_alphanumeric() || "-._".contains(x))
+}
+
+#[cfg(feature = "msgpack")]
+impl Node<'_> {
+  /// Converts to MessagePack. Numbers become floats, strings are unescaped,
+  /// and objects become maps with string keys.
+  pub fn to_msgpack(&self) -> Vec<u8> {
+    rmp_serde::to_vec(self).expect("msgpack serialization of a node cannot fail")
+  }
+
+  /// Converts MessagePack back to a node, the inverse of `to_msgpack`.
+  /// Maps must have string keys, and floats must be finite.
+  pub fn from_msgpack(bytes: &[u8]) -> Result<Node<'static>, DecodeError> {
+    rmp_serde::from_slice(bytes).map_err(DecodeError)
+  }
+}
+
+#[cfg(feature = "msgpack")]
+impl Serialize for Node<'_> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    match self {
+      Value(x) => match x.as_ref() {
+        "null" => serializer.serialize_unit(),
+        "true" => serializer.serialize_bool(true),
+        "false" => serializer.serialize_bool(false),
+        x if x.starts_with('"') => serializer.serialize_str(&unescape_value(unquote(x))),
+        x => serializer.serialize_f64(x.parse().unwrap_or(f64::NAN)),
+      },
+      Array(xs) => {
+        let mut seq = serializer.serialize_seq(Some(xs.len()))?;
+        xs.iter().try_for_each(|x| seq.serialize_element(x))?;
+        seq.end()
+      }
+      Object(xs) => {
+        let mut map = serializer.serialize_map(Some(xs.len()))?;
+        xs.iter()
+          .try_for_each(|(key, x)| map.serialize_entry(&unescape_value(unquote(key)), x))?;
+        map.end()
+      }
+    }
+  }
+}
+
+#[cfg(feature = "msgpack")]
+impl<'de> Deserialize<'de> for Node<'static> {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    deserializer.deserialize_any(NodeVisitor)
+  }
+}
+
+#[cfg(feature = "msgpack")]
+struct NodeVisitor;
+
+#[cfg(feature = "msgpack")]
+impl<'de> Visitor<'de> for NodeVisitor {
+  type Value = Node<'static>;
+
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str("a JSON compatible value")
+  }
+
+  fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+    Ok(Value("null".into()))
+  }
+
+  fn visit_bool<E: de::Error>(self, x: bool) -> Result<Self::Value, E> {
+    Ok(Value(x.to_string().into()))
+  }
+
+  fn visit_i64<E: de::Error>(self, x: i64) -> Result<Self::Value, E> {
+    Ok(Value(x.to_string().into()))
+  }
+
+  fn visit_u64<E: de::Error>(self, x: u64) -> Result<Self::Value, E> {
+    Ok(Value(x.to_string().into()))
+  }
+
+  fn visit_f64<E: de::Error>(self, x: f64) -> Result<Self::Value, E> {
+    if !x.is_finite() {
+      return Err(E::custom(format!("number is not finite: {}", x)));
+    }
+    Ok(Value(x.to_string().into()))
+  }
+
+  fn visit_str<E: de::Error>(self, x: &str) -> Result<Self::Value, E> {
+    Ok(Value(quote_value(x).into()))
+  }
+
+  fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+    let mut xs = vec![];
+    while let Some(x) = seq.next_element()? {
+      xs.push(x);
+    }
+    Ok(Array(xs))
+  }
+
+  fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+    let mut xs = vec![];
+    while let Some((key, x)) = map.next_entry::<String, _>()? {
+      xs.push((quote_value(&key).into(), x));
+    }
+    Ok(Object(xs))
+  }
+}
+
+/// Quotes `s` as a JSON string, escaping where needed.
+pub(crate) fn quote_value(s: &str) -> String {
+  let mut buf = String::with_capacity(s.len() + 2);
+  buf.push('"');
+  for c in s.chars() {
+    match c {
+      '"' => buf.push_str("\\\""),
+      '\\' => buf.push_str("\\\\"),
+      '\n' => buf.push_str("\\n"),
+      '\r' => buf.push_str("\\r"),
+      '\t' => buf.push_str("\\t"),
+      c if c < ' ' => buf.push_str(&format!("\\u{:04x}", c as u32)),
+      c => buf.push(c),
+    }
+  }
+  buf.push('"');
+  buf
+}
+
+fn template_value<'a>(node: &'a Node, key: &str) -> Result<Cow<'a, str>, TemplateError> {
+  let node = key.split('.').try_fold(node, |node, name| match node {
+    Object(xs) => xs
+      .iter()
+      .find(|(k, _)| unquote(k) == name)
+      .map(|(_, v)| v)
+      .ok_or_else(|| TemplateError::UnknownKey(key.to_owned())),
+    _ => Err(TemplateError::UnknownKey(key.to_owned())),
+  })?;
+  match node {
+    Value(x) if x.starts_with('"') => Ok(Cow::Owned(unescape_value(unquote(x)))),
+    Value(x) => Ok(Cow::Borrowed(x)),
+    _ => Err(TemplateError::NotAValue(key.to_owned())),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{
+    CsvError, CsvOptions, DotenvError,
+    TemplateError::{self, NotAValue, UnknownKey},
+  };
+  use crate::parse::parse;
+
+  #[test]
+  fn to_template_string() {
+    let tests: Vec<(&str, &str, Result<&str, TemplateError>)> = vec![
+      ("{}", "", Ok("")),
+      ("{}", "hello", Ok("hello")),
+      (r#"{"a":1}"#, "{{a}}", Ok("1")),
+      (r#"{"a":1}"#, "{{ a }}", Ok("1")),
+      (r#"{"a":1}"#, "{{a", Ok("{{a")),
+      (
+        r#"{"name":"bob","age":42}"#,
+        "{{name}} is {{age}} years old",
+        Ok("bob is 42 years old"),
+      ),
+      (r#"{"a":{"b":{"c":true}}}"#, "c={{a.b.c}}", Ok("c=true")),
+      (r#"{"a":"\"x\ny\u00e9"}"#, "{{a}}", Ok("\"x\ny\u{e9}")),
+      (r#"{"a":1}"#, "{{b}}", Err(UnknownKey("b".to_owned()))),
+      (r#"{"a":1}"#, "{{a.b}}", Err(UnknownKey("a.b".to_owned()))),
+      (r#"{"a":[1,2]}"#, "{{a}}", Err(NotAValue("a".to_owned()))),
+      (r#"{"a":{"b":1}}"#, "{{a}}", Err(NotAValue("a".to_owned()))),
+      ("[1]", "{{a}}", Err(UnknownKey("a".to_owned()))),
+    ];
+
+    for (input, template, expected) in tests {
+      let actual = parse(input).unwrap().to_template_string(template);
+      assert_eq!(
+        actual,
+        expected.map(|x| x.to_owned()),
+        "\n    input: `{}`\n template: `{}`\n",
+        input,
+        template,
+      );
+    }
+  }
+
+  #[test]
+  fn to_csv_with_options() {
+    let tab = CsvOptions {
+      delimiter: '\t',
+      ..Default::default()
+    };
+    let custom = CsvOptions {
+      delimiter: ';',
+      quote: '\'',
+      null_value: "NULL".to_owned(),
+      header: false,
+    };
+    let tests = vec![
+      (CsvOptions::default(), "[]", Ok("")),
+      (
+        CsvOptions::default(),
+        r#"[{"a":1,"b":"x"},{"b":"y","c":true},{"a":null}]"#,
+        Ok("a,b,c\n1,x,\n,y,true\n,,"),
+      ),
+      (
+        CsvOptions::default(),
+        r#"[{"a":"x,y","b":"say \"hi\"","c":"1\n2"}]"#,
+        Ok("a,b,c\n\"x,y\",\"say \"\"hi\"\"\",\"1\n2\""),
+      ),
+      (tab, r#"[{"a":1,"b":"x,y"}]"#, Ok("a\tb\n1\tx,y")),
+      (
+        custom,
+        r#"[{"a":null,"b":"x;y","c":"it's"}]"#,
+        Ok("NULL;'x;y';'it''s'"),
+      ),
+      (CsvOptions::default(), "{}", Err(CsvError::NotAnArray)),
+      (
+        CsvOptions::default(),
+        "[{},1]",
+        Err(CsvError::NotAnObject(1)),
+      ),
+      (
+        CsvOptions::default(),
+        r#"[{"a":1},{"a":[]}]"#,
+        Err(CsvError::NestedValue(1, "a".to_owned())),
+      ),
+    ];
+
+    for (options, input, expected) in tests {
+      let actual = parse(input).unwrap().to_csv_with_options(&options);
+      assert_eq!(
+        actual,
+        expected.map(|x| x.to_owned()),
+        "\n input: `{}`\n",
+        input
+      );
+    }
+  }
+
+  #[cfg(feature = "xml")]
+  #[test]
+  fn to_xml() {
+    let tests = vec![
+      ("1", Ok("<root>1</root>")),
+      ("null", Ok("<root/>")),
+      (r#""a<b""#, Ok("<root>a&lt;b</root>")),
+      (r#""a\nb""#, Ok("<root>a\nb</root>")),
+      ("{}", Ok("<root></root>")),
+      ("[]", Ok("<root></root>")),
+      (
+        r#"{"name":"foo","values":[1,2,3]}"#,
+        Ok(concat!(
+          "<root><name>foo</name><values>",
+          "<item>1</item><item>2</item><item>3</item>",
+          "</values></root>",
+        )),
+      ),
+      (
+        r#"[{"a":{"b":true}},null]"#,
+        Ok("<root><item><a><b>true</b></a></item><item/></root>"),
+      ),
+      (r#"{"a b":1}"#, Err("invalid xml element name: a b")),
+      (r#"{"1":1}"#, Err("invalid xml element name: 1")),
+    ];
+
+    for (input, expected) in tests {
+      let actual = parse(input).unwrap().to_xml().map_err(|e| e.to_string());
+      assert_eq!(
+        actual,
+        expected.map(|x| x.to_owned()).map_err(|x| x.to_owned()),
+        "\n input: `{}`\n",
+        input,
+      );
+    }
+  }
+
+  #[test]
+  fn to_dotenv_string() {
+    let tests = vec![
+      ("", "{}", Ok("")),
+      (
+        "",
+        r#"{"DB_HOST":"localhost","DB_PORT":5432}"#,
+        Ok("DB_HOST=\"localhost\"\nDB_PORT=5432"),
+      ),
+      (
+        "",
+        r#"{"db":{"host":"local\"host","port":1},"debug":true,"x":null}"#,
+        Ok("DB_HOST=\"local\\\"host\"\nDB_PORT=1\nDEBUG=true\nX="),
+      ),
+      (
+        "APP_",
+        r#"{"log-level":"info"}"#,
+        Ok("APP_LOG_LEVEL=\"info\""),
+      ),
+      ("", "[]", Err(DotenvError::NotAnObject)),
+      (
+        "",
+        r#"{"a":[1]}"#,
+        Err(DotenvError::TooDeep("a".to_owned())),
+      ),
+      (
+        "",
+        r#"{"a":{"b":{"c":1}}}"#,
+        Err(DotenvError::TooDeep("a.b".to_owned())),
+      ),
+    ];
+    for (prefix, input, expected) in tests {
+      let actual = parse(input).unwrap().to_dotenv_string(prefix);
+      assert_eq!(actual, expected.map(str::to_owned), "input: {}", input);
+    }
+  }
+
+  #[test]
+  fn from_vars() {
+    use crate::node::Node;
+
+    let vars = [
+      ("JSONSRT_TEST_DB_PORT", "5432"),
+      ("JSONSRT_TEST_DB_HOST", "local\"host"),
+      ("JSONSRT_TESTX", "ignored"),
+    ]
+    .map(|(name, value)| (name.to_owned(), value.to_owned()));
+    assert_eq!(
+      Node::from_vars("JSONSRT_TEST", vars.clone()),
+      parse(r#"{"db_host":"local\"host","db_port":"5432"}"#).unwrap()
+    );
+    assert_eq!(Node::from_vars("JSONSRT_NONE", vars), parse("{}").unwrap());
+  }
+
+  #[cfg(feature = "msgpack")]
+  #[test]
+  fn to_msgpack() {
+    use crate::node::Node;
+
+    let tests = [
+      ("null", vec![0xc0]),
+      ("true", vec![0xc3]),
+      ("1", vec![0xcb, 0x3f, 0xf0, 0, 0, 0, 0, 0, 0]),
+      (r#""a\n""#, vec![0xa2, b'a', b'\n']),
+      ("[]", vec![0x90]),
+      (r#"{"a":[false]}"#, vec![0x81, 0xa1, b'a', 0x91, 0xc2]),
+    ];
+    for (input, expected) in tests {
+      assert_eq!(
+        parse(input).unwrap().to_msgpack(),
+        expected,
+        "input: {}",
+        input
+      );
+    }
+
+    let input = r#"{"s":"a\"\\b\u0001é","n":[-2,2.5,1e3],"b":true,"z":null,"o":{}}"#;
+    let expected = r#"{"s":"a\"\\b\u0001é","n":[-2,2.5,1000],"b":true,"z":null,"o":{}}"#;
+    let node = parse(input).unwrap();
+    assert_eq!(
+      Node::from_msgpack(&node.to_msgpack()).unwrap(),
+      parse(expected).unwrap()
+    );
+    assert!(Node::from_msgpack(&[0xc4, 0x01, 0x00]).is_err());
+    assert!(Node::from_msgpack(&[0x81, 0x01, 0xc0]).is_err());
+  }
+}
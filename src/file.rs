@@ -0,0 +1,111 @@
+use crate::{
+  node::{Node, OwnedNode},
+  parse::{parse, ParseError},
+  sort::SortOptions,
+};
+use std::{
+  error::Error,
+  fmt, fs,
+  io::{self, Write},
+  path::Path,
+};
+
+#[derive(Debug)]
+pub enum FileError {
+  Io(io::Error),
+  Parse(ParseError),
+}
+
+impl fmt::Display for FileError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      FileError::Io(e) => write!(f, "{}", e),
+      FileError::Parse(e) => write!(f, "{}", e),
+    }
+  }
+}
+
+impl Error for FileError {}
+
+impl Node<'_> {
+  /// Reads and parses a JSON file, owning the result so it outlives the
+  /// file contents.
+  pub fn parse_from_file(path: &Path) -> Result<OwnedNode, FileError> {
+    let input = fs::read_to_string(path).map_err(FileError::Io)?;
+    parse(&input)
+      .map(|x| x.to_owned())
+      .map_err(FileError::Parse)
+  }
+
+  /// Writes a copy sorted by key names to `path`, formatted with a trailing
+  /// newline, see `write_atomically`.
+  pub fn write_sorted_to_file(&self, path: &Path, options: &SortOptions) -> io::Result<()> {
+    let mut node = self.clone();
+    node.sort_by_name(options);
+    write_atomically(path, &(node.to_string() + "\n"))
+  }
+}
+
+/// Writes to a temporary file next to `path` and renames it over `path`, so
+/// an interrupted write never leaves a truncated file. An existing file
+/// keeps its permissions.
+pub fn write_atomically(path: &Path, contents: &str) -> io::Result<()> {
+  let dir = match path.parent() {
+    Some(dir) if !dir.as_os_str().is_empty() => dir,
+    _ => Path::new("."),
+  };
+  let mut temp = tempfile::Builder::new().tempfile_in(dir)?;
+  temp.write_all(contents.as_bytes())?;
+  if let Ok(metadata) = fs::metadata(path) {
+    temp.as_file().set_permissions(metadata.permissions())?;
+  }
+  temp.persist(path).map_err(|e| e.error)?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::FileError;
+  use crate::{parse::parse, Node, SortOptions};
+  use std::{fs, io, path::Path};
+  use tempfile::NamedTempFile;
+
+  #[test]
+  fn parse_from_file() -> io::Result<()> {
+    let file = NamedTempFile::new()?;
+    fs::write(file.path(), r#"{"b":1,"a":[2]}"#)?;
+    let node = Node::parse_from_file(file.path()).unwrap();
+    drop(file);
+    assert_eq!(node, parse(r#"{"b":1,"a":[2]}"#).unwrap());
+
+    let file = NamedTempFile::new()?;
+    fs::write(file.path(), "[1,")?;
+    assert!(matches!(
+      Node::parse_from_file(file.path()),
+      Err(FileError::Parse(e)) if e.offset == 3
+    ));
+    assert!(matches!(
+      Node::parse_from_file(Path::new("/nonexistent")),
+      Err(FileError::Io(_))
+    ));
+    Ok(())
+  }
+
+  #[test]
+  fn write_sorted_to_file() -> io::Result<()> {
+    let file = NamedTempFile::new()?;
+    fs::write(file.path(), "old")?;
+    let node = parse(r#"{"b":1,"a":{"d":2,"c":3}}"#).unwrap();
+    let options = SortOptions {
+      reverse: true,
+      ..Default::default()
+    };
+    node.write_sorted_to_file(file.path(), &options)?;
+    assert_eq!(
+      fs::read_to_string(file.path())?,
+      "{\n  \"b\": 1,\n  \"a\": {\n    \"d\": 2,\n    \"c\": 3\n  }\n}\n"
+    );
+    assert_eq!(node, parse(r#"{"b":1,"a":{"d":2,"c":3}}"#).unwrap());
+    Ok(())
+  }
+}
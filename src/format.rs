@@ -1,62 +1,615 @@
-use crate::node::Node::{self, Array, Object, Value};
+use crate::{
+  export::quote_value,
+  node::{
+    pointer_token,
+    Node::{self, Array, Object, Value},
+  },
+  sort::{unescape_value, unquote},
+};
+use std::{
+  borrow::Cow,
+  collections::HashMap,
+  fmt,
+  io::{self, Write},
+};
 
-impl ToString for Node<'_> {
-  fn to_string(&self) -> String {
-    let mut buf = String::new();
-    self.format(&mut buf, "  ", 0, false);
-    buf
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatOptions {
+  /// Write everything on one line without spaces. The indent, alignment,
+  /// blank line and comment options are ignored.
+  pub compact: bool,
+
+  /// Write arrays and objects compactly when that takes fewer bytes than
+  /// this, with the other options such as `mask_keys` applied.
+  pub compact_below: Option<usize>,
+
+  /// Write arrays and objects holding only values, like `["a","b"]`,
+  /// compactly, and the others on multiple lines, as Elm does.
+  pub elm_style: bool,
+
+  /// Text written once per nesting level, two spaces by default.
+  pub indent: String,
+
+  /// Separate object keys from their values with `": "` instead of `":"`.
+  pub space_after_colon: bool,
+
+  /// Pad object keys so the values of each object line up in one column.
+  pub align_values: bool,
+
+  /// Pad numbers on the left so they line up on the right with the longest
+  /// value of their object, like a column of figures.
+  pub align_numbers: bool,
+
+  /// Put an empty line between the entries of objects.
+  pub blank_line_between_entries: bool,
+
+  /// Put a `// <JSON Pointer>` comment line before each object entry. The
+  /// output is no longer valid JSON.
+  pub path_comments: bool,
+
+  /// Replace string values longer than this many characters with
+  /// `"<truncated, N chars>"`. This loses data, so it is for display only.
+  pub max_string_length_display: Option<usize>,
+
+  /// Write `\n` and `\t` in string values as actual newlines and tabs, for
+  /// reading multi-line text. The output is no longer valid JSON, so it is
+  /// for display only.
+  pub unescape_string_newlines: bool,
+
+  /// Escape U+2028 and U+2029 in string values as `\u2028` and `\u2029`,
+  /// since they end string literals in older JavaScript.
+  pub escape_line_separators: bool,
+
+  /// Write numbers with an integer value, like `1.0` or `1e2`, as integers.
+  /// Numbers beyond 2^53 are kept as they are, since they may not be exact.
+  pub normalize_integer_floats: bool,
+
+  /// Write integers of more than 15 digits as strings, like
+  /// `"12345678901234567"`, since JavaScript's `JSON.parse` rounds integers
+  /// beyond 2^53.
+  pub bigint_as_string: bool,
+
+  /// Write strings holding integers of more than 15 digits as numbers,
+  /// undoing `bigint_as_string`.
+  pub unquote_bigints: bool,
+
+  /// End the output with a newline when the top level node is an object,
+  /// as `jq` does, so `{"a":1}` gives `{\n  "a": 1\n}\n` instead of
+  /// `{\n  "a": 1\n}`. Only the top level node is affected, nested objects
+  /// and top level arrays and values are written as before. There is no
+  /// command line flag, as the command always ends its output with a newline.
+  pub object_trailing_newline: bool,
+
+  /// Write the items of arrays on their own lines after this prefix, like
+  /// `- ` for YAML style lists, without brackets or commas. Objects keep
+  /// their braces and commas, so the output mixes the two, like `- {...},`,
+  /// and is neither JSON nor YAML. It is for display only.
+  pub array_item_prefix: Option<String>,
+
+  /// Write object keys under other names, looked up by their unquoted
+  /// source text, like `usr_nm` to `user_name`. The node is not changed.
+  pub key_aliases: HashMap<String, String>,
+
+  /// Write the values of object entries with these unquoted keys as
+  /// `"***"`, like passwords in logged configs. The node is not changed.
+  pub mask_keys: Vec<String>,
+}
+
+impl Default for FormatOptions {
+  fn default() -> Self {
+    FormatOptions {
+      compact: false,
+      compact_below: None,
+      elm_style: false,
+      indent: "  ".to_owned(),
+      space_after_colon: true,
+      align_values: false,
+      align_numbers: false,
+      blank_line_between_entries: false,
+      path_comments: false,
+      max_string_length_display: None,
+      unescape_string_newlines: false,
+      escape_line_separators: false,
+      normalize_integer_floats: false,
+      bigint_as_string: false,
+      unquote_bigints: false,
+      object_trailing_newline: false,
+      array_item_prefix: None,
+      key_aliases: HashMap::new(),
+      mask_keys: vec![],
+    }
+  }
+}
+
+/// Receives formatted output in pieces, see `Node::to_string_stream`.
+pub trait TokenSink {
+  fn write_token(&mut self, token: &str) -> io::Result<()>;
+}
+
+impl TokenSink for String {
+  fn write_token(&mut self, token: &str) -> io::Result<()> {
+    self.push_str(token);
+    Ok(())
+  }
+}
+
+/// A `TokenSink` writing to an `io::Write`. Wrap unbuffered writers like
+/// files and sockets in a `BufWriter`, as tokens are small.
+pub struct WriterSink<W: Write>(pub W);
+
+impl<W: Write> TokenSink for WriterSink<W> {
+  fn write_token(&mut self, token: &str) -> io::Result<()> {
+    self.0.write_all(token.as_bytes())
+  }
+}
+
+/// A `TokenSink` counting the bytes written, which fails once there are
+/// `limit` or more, so long output is not formatted to the end.
+struct LengthSink {
+  len: usize,
+  limit: usize,
+}
+
+impl TokenSink for LengthSink {
+  fn write_token(&mut self, token: &str) -> io::Result<()> {
+    self.len += token.len();
+    match self.len < self.limit {
+      true => Ok(()),
+      false => Err(io::ErrorKind::Other.into()),
+    }
+  }
+}
+
+impl fmt::Display for Node<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(&self.to_string_with_options(&FormatOptions::default()))
   }
 }
 
 impl Node<'_> {
-  fn format(&self, buf: &mut String, indent: &str, level: usize, apply_initial_indent: bool) {
+  pub fn to_string_with_options(&self, options: &FormatOptions) -> String {
+    let mut buf = String::new();
+    self
+      .to_string_stream(&mut buf, options)
+      .expect("writing to a string does not fail");
+    buf
+  }
+
+  /// Formats like `to_string_with_options`, but hands the output to `sink`
+  /// piece by piece instead of building a string, so large documents can be
+  /// written as they are formatted.
+  pub fn to_string_stream<S: TokenSink>(
+    &self,
+    sink: &mut S,
+    options: &FormatOptions,
+  ) -> io::Result<()> {
+    self.format(sink, options, None, &mut vec![], 0, false)
+  }
+
+  /// Formats on a single line without spaces.
+  pub fn to_compact_string(&self) -> String {
+    self.to_string_with_options(&FormatOptions {
+      compact: true,
+      ..Default::default()
+    })
+  }
+
+  /// Formats with the numbers of each object right aligned, see
+  /// `FormatOptions::align_numbers`.
+  pub fn to_string_with_numeric_key_alignment(&self) -> String {
+    self.to_string_with_options(&FormatOptions {
+      align_numbers: true,
+      ..Default::default()
+    })
+  }
+
+  /// Formats with large integers as strings, see
+  /// `FormatOptions::bigint_as_string`.
+  pub fn to_string_with_bigint_support(&self) -> String {
+    self.to_string_with_options(&FormatOptions {
+      bigint_as_string: true,
+      ..Default::default()
+    })
+  }
+
+  /// Formats with escaped newlines and tabs in strings written as they are,
+  /// see `FormatOptions::unescape_string_newlines`.
+  pub fn to_string_with_pretty_strings(&self) -> String {
+    self.to_string_with_options(&FormatOptions {
+      unescape_string_newlines: true,
+      ..Default::default()
+    })
+  }
+
+  /// Formats followed by a line like `// stats: {objects:1, arrays:0,
+  /// values:2, depth:1}` from `count`. The output is JSONC, not JSON.
+  pub fn to_string_with_statistics_footer(&self, options: &FormatOptions) -> String {
+    format!(
+      "{}\n// stats: {}",
+      self.to_string_with_options(options),
+      self.count()
+    )
+  }
+
+  /// Formats in the Elm style, see `FormatOptions::elm_style`.
+  pub fn to_string_pretty_but_compact_scalars(&self) -> String {
+    self.to_string_with_options(&FormatOptions {
+      elm_style: true,
+      ..Default::default()
+    })
+  }
+
+  /// Formats with a `//` comment before each object entry holding the
+  /// `description`, or else the `title`, of the matching property in the
+  /// JSON Schema. The output is no longer valid JSON.
+  pub fn to_string_with_schema_docs(&self, schema: &Node, options: &FormatOptions) -> String {
+    let mut buf = String::new();
+    self
+      .format(&mut buf, options, Some(schema), &mut vec![], 0, false)
+      .expect("writing to a string does not fail");
+    buf
+  }
+
+  /// Formats a copy of this node with its objects sorted in the order of
+  /// the matching JSON Schema, see `sort_by_schema`.
+  pub fn to_sorted_with_schema_order(&self, schema: &Node, options: &FormatOptions) -> String {
+    let mut node = self.clone();
+    node.sort_by_schema(schema);
+    node.to_string_with_options(options)
+  }
+
+  /// Whether this node is written as a list of prefixed items, which starts
+  /// on a new line.
+  fn is_item_list(&self, options: &FormatOptions) -> bool {
+    match self {
+      Array(xs) => {
+        options.array_item_prefix.is_some()
+          && !options.compact
+          && !xs.is_empty()
+          && !self.is_compacted(options)
+      }
+      _ => false,
+    }
+  }
+
+  /// Whether this array or object is written compactly because of
+  /// `compact_below` or `elm_style`, while the rest is not.
+  fn is_compacted(&self, options: &FormatOptions) -> bool {
+    match self {
+      Value(_) => false,
+      _ if options.compact => false,
+      _ if matches!(options.compact_below, Some(x) if self.is_compact_len_below(options, x)) => {
+        true
+      }
+      Array(xs) => options.elm_style && xs.iter().all(|x| matches!(x, Value(_))),
+      Object(xs) => options.elm_style && xs.iter().all(|(_, x)| matches!(x, Value(_))),
+    }
+  }
+
+  /// Whether this node written compactly with `options` is shorter than
+  /// `limit` bytes.
+  fn is_compact_len_below(&self, options: &FormatOptions, limit: usize) -> bool {
+    let options = FormatOptions {
+      compact: true,
+      ..options.clone()
+    };
+    let mut sink = LengthSink { len: 0, limit };
+    self
+      .format(&mut sink, &options, None, &mut vec![], 0, false)
+      .is_ok()
+  }
+
+  fn format<S: TokenSink>(
+    &self,
+    sink: &mut S,
+    options: &FormatOptions,
+    schema: Option<&Node>,
+    path: &mut Vec<String>,
+    level: usize,
+    apply_initial_indent: bool,
+  ) -> io::Result<()> {
+    let (indent, newline) = match options.compact {
+      true => ("", ""),
+      false => (options.indent.as_str(), "\n"),
+    };
     let print_indent =
-      |level: usize, buf: &mut String| (0..level).for_each(|_| buf.push_str(indent));
+      |level: usize, sink: &mut S| (0..level).try_for_each(|_| sink.write_token(indent));
 
     if apply_initial_indent {
-      print_indent(level, buf);
+      print_indent(level, sink)?;
+    }
+
+    if self.is_compacted(options) {
+      let options = FormatOptions {
+        compact: true,
+        ..options.clone()
+      };
+      return self.format(sink, &options, schema, path, level, false);
     }
 
     match self {
-      Value(x) => buf.push_str(x),
+      Value(x) => match options.max_string_length_display {
+        Some(max) if x.starts_with('"') && unquote(x).chars().count() > max => sink.write_token(
+          &format!("\"<truncated, {} chars>\"", unquote(x).chars().count()),
+        )?,
+        // The string and number transforms are independent of each other,
+        // so apply all that are enabled in turn.
+        _ => {
+          let mut x = Cow::Borrowed(x.as_ref());
+          if x.starts_with('"') {
+            if options.unescape_string_newlines {
+              x = unescape_newlines(&x).into();
+            }
+            if options.escape_line_separators {
+              x = x
+                .replace('\u{2028}', "\\u2028")
+                .replace('\u{2029}', "\\u2029")
+                .into();
+            }
+            if options.unquote_bigints && is_bigint(unquote(&x)) {
+              x = unquote(&x).to_owned().into();
+            }
+          } else if options.bigint_as_string && is_bigint(&x) {
+            x = format!("\"{}\"", x).into();
+          } else if options.normalize_integer_floats {
+            if let Some(n) = integer_float(&x) {
+              x = n.to_string().into();
+            }
+          }
+          sink.write_token(&x)?
+        }
+      },
 
-      Array(xs) if xs.is_empty() => buf.push_str("[]"),
+      Array(xs) if xs.is_empty() => sink.write_token("[]")?,
+      Array(xs) if self.is_item_list(options) => {
+        let prefix = options.array_item_prefix.as_deref().unwrap_or_default();
+        for (i, x) in xs.iter().enumerate() {
+          if i > 0 || level > 0 {
+            sink.write_token("\n")?;
+          }
+          print_indent(level, sink)?;
+          // Nested lists start on the next line, so avoid trailing spaces.
+          match x.is_item_list(options) {
+            true => sink.write_token(prefix.trim_end())?,
+            false => sink.write_token(prefix)?,
+          }
+          path.push(i.to_string());
+          x.format(
+            sink,
+            options,
+            schema.and_then(|x| x.get("items")),
+            path,
+            level + 1,
+            false,
+          )?;
+          path.pop();
+        }
+      }
       Array(xs) => {
-        buf.push_str("[\n");
-        xs.iter().enumerate().for_each(|(i, x)| {
-          x.format(buf, indent, level + 1, true);
+        let items = schema.and_then(|x| x.get("items"));
+        sink.write_token("[")?;
+        sink.write_token(newline)?;
+        for (i, x) in xs.iter().enumerate() {
+          path.push(i.to_string());
+          x.format(sink, options, items, path, level + 1, true)?;
+          path.pop();
           if i < xs.len() - 1 {
-            buf.push_str(",\n")
+            sink.write_token(",")?;
+            sink.write_token(newline)?;
           }
-        });
-        buf.push_str("\n");
-        print_indent(level, buf);
-        buf.push_str("]");
+        }
+        sink.write_token(newline)?;
+        print_indent(level, sink)?;
+        sink.write_token("]")?;
       }
 
-      Object(xs) if xs.is_empty() => buf.push_str("{}"),
+      Object(xs) if xs.is_empty() => sink.write_token("{}")?,
       Object(xs) => {
-        buf.push_str("{\n");
-        xs.iter().enumerate().for_each(|(i, (key, val))| {
-          print_indent(level + 1, buf);
-          buf.push_str(key);
-          buf.push_str(": ");
-          val.format(buf, indent, level + 1, false);
+        let alias = |key: &str| {
+          options
+            .key_aliases
+            .get(unquote(key))
+            .map(|x| quote_value(x))
+        };
+        let key_width = |key: &str| key.chars().count();
+        let max_key_width = match options.align_values && !options.compact {
+          true => xs
+            .iter()
+            .map(|(key, _)| key_width(alias(key).as_deref().unwrap_or(key)))
+            .max()
+            .unwrap_or(0),
+          false => 0,
+        };
+        let masked = |key: &str| options.mask_keys.iter().any(|x| x == unquote(key));
+        let value_width = |key: &str, val: &Node| match (masked(key), val) {
+          (true, _) => 5,
+          (false, Value(_)) => val.to_string_with_options(options).chars().count(),
+          (false, _) => 0,
+        };
+        let max_value_width = match options.align_numbers && !options.compact {
+          true => xs
+            .iter()
+            .map(|(key, val)| value_width(key, val))
+            .max()
+            .unwrap_or(0),
+          false => 0,
+        };
+
+        let properties = schema.and_then(|x| x.get("properties"));
+        sink.write_token("{")?;
+        sink.write_token(newline)?;
+        for (i, (key, val)) in xs.iter().enumerate() {
+          let property = properties.and_then(|x| x.get(unquote(key)));
+          path.push(pointer_token(unquote(key)));
+          if options.path_comments && !options.compact {
+            print_indent(level + 1, sink)?;
+            sink.write_token("// /")?;
+            sink.write_token(&path.join("/"))?;
+            sink.write_token("\n")?;
+          }
+          let doc = property.and_then(|x| x.get("description").or_else(|| x.get("title")));
+          if let (Some(Value(doc)), false) = (doc, options.compact) {
+            for line in unescape_value(unquote(doc)).lines() {
+              print_indent(level + 1, sink)?;
+              sink.write_token("// ")?;
+              sink.write_token(line)?;
+              sink.write_token("\n")?;
+            }
+          }
+          print_indent(level + 1, sink)?;
+          let masked = masked(key);
+          let item_list = !masked && val.is_item_list(options);
+          let alias = alias(key);
+          let key = alias.as_deref().unwrap_or(key);
+          sink.write_token(key)?;
+          if item_list {
+            sink.write_token(":")?;
+          } else {
+            sink.write_token(match options.space_after_colon && !options.compact {
+              true => ": ",
+              false => ":",
+            })?;
+          }
+          if options.align_values && !item_list {
+            (key_width(key)..max_key_width).try_for_each(|_| sink.write_token(" "))?;
+          }
+          if !masked && is_number(val) {
+            (value_width(key, val)..max_value_width).try_for_each(|_| sink.write_token(" "))?;
+          }
+          match masked {
+            true => sink.write_token("\"***\"")?,
+            false => val.format(sink, options, property, path, level + 1, false)?,
+          }
+          path.pop();
           if i < xs.len() - 1 {
-            buf.push_str(",\n")
+            sink.write_token(",")?;
+            sink.write_token(newline)?;
+            if options.blank_line_between_entries {
+              sink.write_token(newline)?;
+            }
           }
-        });
-        buf.push_str("\n");
-        print_indent(level, buf);
-        buf.push_str("}");
+        }
+        sink.write_token(newline)?;
+        print_indent(level, sink)?;
+        sink.write_token("}")?;
       }
     }
+
+    if let (true, 0, Object(_)) = (options.object_trailing_newline, level, self) {
+      sink.write_token("\n")?;
+    }
+    Ok(())
   }
 }
 
+fn is_number(node: &Node) -> bool {
+  matches!(node, Value(x) if !x.starts_with('"') && !matches!(x.as_ref(), "true" | "false" | "null"))
+}
+
+/// Replaces the `\n` and `\t` escapes in `x` with the characters they stand
+/// for, leaving other escapes, like `\\n`, as they are.
+fn unescape_newlines(x: &str) -> String {
+  let mut buf = String::with_capacity(x.len());
+  let mut chars = x.chars();
+  while let Some(c) = chars.next() {
+    match c {
+      '\\' => match chars.next() {
+        Some('n') => buf.push('\n'),
+        Some('t') => buf.push('\t'),
+        Some(x) => {
+          buf.push('\\');
+          buf.push(x);
+        }
+        None => buf.push('\\'),
+      },
+      c => buf.push(c),
+    }
+  }
+  buf
+}
+
+/// Whether `x` is an integer of more than 15 digits, which may not survive a
+/// round trip through `f64`.
+fn is_bigint(x: &str) -> bool {
+  let digits = x.strip_prefix('-').unwrap_or(x);
+  digits.len() > 15 && digits.bytes().all(|x| x.is_ascii_digit())
+}
+
+/// Returns the integer value of a number written with a fraction or
+/// exponent, if it has one that `f64` holds exactly.
+fn integer_float(x: &str) -> Option<i64> {
+  if !x.contains(['.', 'e', 'E']) {
+    return None;
+  }
+  let n = x.parse::<f64>().ok()?;
+  (n.fract() == 0.0 && n.abs() <= (1u64 << 53) as f64).then_some(n as i64)
+}
+
 #[cfg(test)]
 mod tests {
+  use super::{FormatOptions, TokenSink, WriterSink};
   use crate::parse::parse;
+  use std::io;
+
+  #[test]
+  fn to_string_stream() {
+    let node = parse(r#"{"a":[1,{"b":null}],"c":"d"}"#).unwrap();
+    let options = FormatOptions {
+      align_values: true,
+      ..Default::default()
+    };
+    let mut sink = WriterSink(vec![]);
+    node.to_string_stream(&mut sink, &options).unwrap();
+    assert_eq!(
+      String::from_utf8(sink.0).unwrap(),
+      node.to_string_with_options(&options)
+    );
+
+    struct FailAfter(usize);
+    impl TokenSink for FailAfter {
+      fn write_token(&mut self, _: &str) -> io::Result<()> {
+        self.0 = self.0.checked_sub(1).ok_or(io::ErrorKind::BrokenPipe)?;
+        Ok(())
+      }
+    }
+    let result = node.to_string_stream(&mut FailAfter(3), &options);
+    assert_eq!(result.map_err(|e| e.kind()), Err(io::ErrorKind::BrokenPipe));
+  }
+
+  #[test]
+  fn to_string_with_schema_docs() {
+    let schema = parse(
+      r#"{"properties":{
+        "name":{"description":"The user's \"full\" name","title":"Name"},
+        "tags":{"items":{"properties":{"k":{"title":"Key"}}}},
+        "note":{"description":"Line 1\nLine 2"},
+        "other":{}
+      }}"#,
+    )
+    .unwrap();
+    let input = r#"{"name":"a","tags":[{"k":1,"v":2}],"note":null,"other":1,"x":1}"#;
+    assert_eq!(
+      parse(input)
+        .unwrap()
+        .to_string_with_schema_docs(&schema, &FormatOptions::default()),
+      r#"{
+  // The user's "full" name
+  "name": "a",
+  "tags": [
+    {
+      // Key
+      "k": 1,
+      "v": 2
+    }
+  ],
+  // Line 1
+  // Line 2
+  "note": null,
+  "other": 1,
+  "x": 1
+}"#
+    );
+  }
 
   #[test]
   fn format() {
@@ -110,4 +663,445 @@ mod tests {
       );
     }
   }
+
+  #[test]
+  fn to_string_with_statistics_footer() {
+    let node = parse(r#"{"a":[1,{"b":null}]}"#).unwrap();
+    assert_eq!(
+      node.to_string_with_statistics_footer(&FormatOptions {
+        compact: true,
+        ..Default::default()
+      }),
+      "{\"a\":[1,{\"b\":null}]}\n// stats: {objects:2, arrays:1, values:2, depth:3}"
+    );
+  }
+
+  #[test]
+  fn unescape_newlines_and_escape_line_separators() {
+    let node = parse("[\"a\\nb\u{2028}c\"]").unwrap();
+    let output = node.to_string_with_options(&FormatOptions {
+      compact: true,
+      unescape_string_newlines: true,
+      escape_line_separators: true,
+      ..Default::default()
+    });
+    assert_eq!(output, "[\"a\nb\\u2028c\"]");
+  }
+
+  #[test]
+  fn unquote_bigints_with_escape_line_separators() {
+    let node = parse("[\"12345678901234567890\", \"a\u{2028}\"]").unwrap();
+    let output = node.to_string_with_options(&FormatOptions {
+      compact: true,
+      unquote_bigints: true,
+      escape_line_separators: true,
+      ..Default::default()
+    });
+    assert_eq!(output, "[12345678901234567890,\"a\\u2028\"]");
+  }
+
+  #[test]
+  fn array_item_prefix_is_neither_json_nor_yaml() {
+    let node = parse(r#"{"a":[{"b":1},{"c":2}],"d":3}"#).unwrap();
+    let output = node.to_string_with_options(&FormatOptions {
+      array_item_prefix: Some("- ".to_owned()),
+      ..Default::default()
+    });
+    assert_eq!(
+      output,
+      "{\n  \"a\":\n  - {\n      \"b\": 1\n    }\n  - {\n      \"c\": 2\n    },\n  \"d\": 3\n}"
+    );
+    assert!(parse(&output).is_err());
+  }
+
+  #[test]
+  fn object_trailing_newline_adds_one_byte() {
+    let node = parse(r#"{"a":[{"b":1}]}"#).unwrap();
+    let with_newline = node.to_string_with_options(&FormatOptions {
+      object_trailing_newline: true,
+      ..Default::default()
+    });
+    assert_eq!(with_newline, node.to_string() + "\n");
+
+    let node = parse(r#"[{"a":1}]"#).unwrap();
+    let with_newline = node.to_string_with_options(&FormatOptions {
+      object_trailing_newline: true,
+      ..Default::default()
+    });
+    assert_eq!(with_newline, node.to_string());
+  }
+
+  #[test]
+  fn format_with_options() {
+    let tests = vec![
+      (
+        FormatOptions {
+          space_after_colon: false,
+          ..Default::default()
+        },
+        r#"{"a":"hello", "b":  [1, {"c" : null}]}"#,
+        r#"{
+  "a":"hello",
+  "b":[
+    1,
+    {
+      "c":null
+    }
+  ]
+}"#,
+      ),
+      (
+        FormatOptions {
+          align_values: true,
+          ..Default::default()
+        },
+        r#"{"short":1, "much_longer": {"a": [{"bb": 2, "c": 3}], "é": 4}}"#,
+        r#"{
+  "short":       1,
+  "much_longer": {
+    "a": [
+      {
+        "bb": 2,
+        "c":  3
+      }
+    ],
+    "é": 4
+  }
+}"#,
+      ),
+      (
+        FormatOptions {
+          space_after_colon: false,
+          align_values: true,
+          ..Default::default()
+        },
+        r#"{"a":1, "bbb": 2}"#,
+        r#"{
+  "a":  1,
+  "bbb":2
+}"#,
+      ),
+      (
+        FormatOptions {
+          align_numbers: true,
+          ..Default::default()
+        },
+        r#"{"a": "hello", "b": 42, "c": [1, 200], "d": -1.5, "e": true, "f": {"g": 1, "h": 10}}"#,
+        r#"{
+  "a": "hello",
+  "b":      42,
+  "c": [
+    1,
+    200
+  ],
+  "d":    -1.5,
+  "e": true,
+  "f": {
+    "g":  1,
+    "h": 10
+  }
+}"#,
+      ),
+      (
+        FormatOptions {
+          align_values: true,
+          align_numbers: true,
+          mask_keys: vec!["pw".to_owned()],
+          ..Default::default()
+        },
+        r#"{"pw": "secret", "count": 7}"#,
+        r#"{
+  "pw":    "***",
+  "count":     7
+}"#,
+      ),
+      (
+        FormatOptions {
+          blank_line_between_entries: true,
+          ..Default::default()
+        },
+        r#"{"a":1, "b": [1, 2], "c": {"d": 3, "e": 4}}"#,
+        r#"{
+  "a": 1,
+
+  "b": [
+    1,
+    2
+  ],
+
+  "c": {
+    "d": 3,
+
+    "e": 4
+  }
+}"#,
+      ),
+      (
+        FormatOptions {
+          path_comments: true,
+          ..Default::default()
+        },
+        r#"{"users": [{"name": "Alice"}], "a/b": 1}"#,
+        r#"{
+  // /users
+  "users": [
+    {
+      // /users/0/name
+      "name": "Alice"
+    }
+  ],
+  // /a~1b
+  "a/b": 1
+}"#,
+      ),
+      (
+        FormatOptions {
+          unescape_string_newlines: true,
+          ..Default::default()
+        },
+        r#"{"a\nb": ["x\ny\tz", "\\n\\\n", "\"\u000a"]}"#,
+        "{\n  \"a\\nb\": [\n    \"x\ny\tz\",\n    \"\\\\n\\\\\n\",\n    \"\\\"\\u000a\"\n  ]\n}",
+      ),
+      (
+        FormatOptions {
+          max_string_length_display: Some(3),
+          ..Default::default()
+        },
+        r#"{"abcd": ["abc", "abcd", "é😀é😀", 12345, true]}"#,
+        r#"{
+  "abcd": [
+    "abc",
+    "<truncated, 4 chars>",
+    "<truncated, 4 chars>",
+    12345,
+    true
+  ]
+}"#,
+      ),
+      (
+        FormatOptions {
+          escape_line_separators: true,
+          ..Default::default()
+        },
+        "[\"a\u{2028}b\u{2029}\", \"\\u2028\"]",
+        r#"[
+  "a\u2028b\u2029",
+  "\u2028"
+]"#,
+      ),
+      (
+        FormatOptions {
+          indent: "\t".to_owned(),
+          ..Default::default()
+        },
+        r#"{"a":[1]}"#,
+        "{\n\t\"a\": [\n\t\t1\n\t]\n}",
+      ),
+      (
+        FormatOptions {
+          normalize_integer_floats: true,
+          ..Default::default()
+        },
+        r#"[1.0, 1.00, 1e0, -2.0E1, 1.5, 3, "1.0", 1e300, -0.0]"#,
+        r#"[
+  1,
+  1,
+  1,
+  -20,
+  1.5,
+  3,
+  "1.0",
+  1e300,
+  0
+]"#,
+      ),
+      (
+        FormatOptions {
+          bigint_as_string: true,
+          compact: true,
+          ..Default::default()
+        },
+        r#"[123456789012345, 1234567890123456, -1234567890123456, 1234567890123456.5, "1234567890123456"]"#,
+        r#"[123456789012345,"1234567890123456","-1234567890123456",1234567890123456.5,"1234567890123456"]"#,
+      ),
+      (
+        FormatOptions {
+          unquote_bigints: true,
+          compact: true,
+          ..Default::default()
+        },
+        r#"["123456789012345", "1234567890123456", "-1234567890123456", "12345678901234x6", 1234567890123456]"#,
+        r#"["123456789012345",1234567890123456,-1234567890123456,"12345678901234x6",1234567890123456]"#,
+      ),
+      (
+        FormatOptions {
+          compact: true,
+          align_values: true,
+          blank_line_between_entries: true,
+          path_comments: true,
+          ..Default::default()
+        },
+        r#" {"a" : 1, "b": [1, 2, {"c": []}], "d": {}} "#,
+        r#"{"a":1,"b":[1,2,{"c":[]}],"d":{}}"#,
+      ),
+      (
+        FormatOptions {
+          compact_below: Some(12),
+          ..Default::default()
+        },
+        r#"{"a": [1, 2], "b": {"c": "hello", "d": [true]}, "e": []}"#,
+        r#"{
+  "a": [1,2],
+  "b": {
+    "c": "hello",
+    "d": [true]
+  },
+  "e": []
+}"#,
+      ),
+      (
+        FormatOptions {
+          elm_style: true,
+          ..Default::default()
+        },
+        r#"{"a": ["x", "y"], "b": {"c": 1, "d": [[], {}]}, "e": [{"f": null}, [true]], "g": 1}"#,
+        r#"{
+  "a": ["x","y"],
+  "b": {
+    "c": 1,
+    "d": [
+      [],
+      {}
+    ]
+  },
+  "e": [
+    {"f":null},
+    [true]
+  ],
+  "g": 1
+}"#,
+      ),
+      (
+        FormatOptions {
+          elm_style: true,
+          ..Default::default()
+        },
+        r#"{"a": 1, "b": "c"}"#,
+        r#"{"a":1,"b":"c"}"#,
+      ),
+      (
+        FormatOptions {
+          compact_below: Some(80),
+          ..Default::default()
+        },
+        r#"{"a": [1, 2]}"#,
+        r#"{"a":[1,2]}"#,
+      ),
+      (
+        FormatOptions {
+          compact_below: Some(15),
+          mask_keys: vec!["b".to_owned()],
+          ..Default::default()
+        },
+        r#"{"a": {"b": "a long secret value"}}"#,
+        r#"{
+  "a": {"b":"***"}
+}"#,
+      ),
+      (
+        FormatOptions {
+          object_trailing_newline: true,
+          ..Default::default()
+        },
+        r#"{"a": {"b": 1}}"#,
+        "{\n  \"a\": {\n    \"b\": 1\n  }\n}\n",
+      ),
+      (
+        FormatOptions {
+          object_trailing_newline: true,
+          ..Default::default()
+        },
+        r#"[{}]"#,
+        "[\n  {}\n]",
+      ),
+      (
+        FormatOptions {
+          compact: true,
+          object_trailing_newline: true,
+          ..Default::default()
+        },
+        r#"{}"#,
+        "{}\n",
+      ),
+      (
+        FormatOptions {
+          array_item_prefix: Some("- ".to_owned()),
+          ..Default::default()
+        },
+        r#"{"a": [1, [2, 3], {"b": []}], "c": 4}"#,
+        r#"{
+  "a":
+  - 1
+  -
+    - 2
+    - 3
+  - {
+      "b": []
+    },
+  "c": 4
+}"#,
+      ),
+      (
+        FormatOptions {
+          array_item_prefix: Some("- ".to_owned()),
+          ..Default::default()
+        },
+        r#"["x", "y"]"#,
+        "- \"x\"\n- \"y\"",
+      ),
+      (
+        FormatOptions {
+          align_values: true,
+          key_aliases: [("a", "alpha"), ("b\\n", "x\"y")]
+            .into_iter()
+            .map(|(k, v)| (k.to_owned(), v.to_owned()))
+            .collect(),
+          ..Default::default()
+        },
+        r#"{"a": 1, "bb": 2, "b\n": {"a": 3}}"#,
+        r#"{
+  "alpha": 1,
+  "bb":    2,
+  "x\"y":  {
+    "alpha": 3
+  }
+}"#,
+      ),
+      (
+        FormatOptions {
+          mask_keys: vec!["password".to_owned(), "token".to_owned()],
+          ..Default::default()
+        },
+        r#"{"user": "a", "password": "b", "db": {"token": {"x": 1}, "port": 1}}"#,
+        r#"{
+  "user": "a",
+  "password": "***",
+  "db": {
+    "token": "***",
+    "port": 1
+  }
+}"#,
+      ),
+    ];
+
+    for (options, input, expected) in tests {
+      let actual = parse(input).map(|x| x.to_string_with_options(&options));
+      assert_eq!(
+        actual.as_ref(),
+        Ok(&expected.to_owned()),
+        "\n input: `{}`\n",
+        input.replace("\n", "\\n"),
+      );
+    }
+  }
 }
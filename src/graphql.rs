@@ -0,0 +1,185 @@
+use crate::{
+  node::Node::{self, Array, Object, Value},
+  sort::unquote,
+};
+use std::{borrow::Cow, error::Error, fmt};
+
+#[derive(Debug, PartialEq)]
+pub enum GraphqlError {
+  NotAnObject,
+  EmptyObject,
+}
+
+impl fmt::Display for GraphqlError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      GraphqlError::NotAnObject => write!(f, "graphql input must be an object"),
+      GraphqlError::EmptyObject => write!(f, "graphql input must have at least one key"),
+    }
+  }
+}
+
+impl Error for GraphqlError {}
+
+type Fields = Vec<(String, String)>;
+
+impl Node<'_> {
+  /// Infers GraphQL type definitions from an example object, starting with
+  /// `type Root`. Nested objects become types named after their keys, and
+  /// arrays are typed by their first element. `null`, empty arrays and
+  /// empty objects have nothing to infer from and are typed as `String`, as
+  /// GraphQL has no empty types. Keys that give the same field name get a
+  /// number suffix, like type names.
+  pub fn to_graphql_schema(&self) -> Result<String, GraphqlError> {
+    let xs = match self {
+      Object(xs) if xs.is_empty() => return Err(GraphqlError::EmptyObject),
+      Object(xs) => xs,
+      _ => return Err(GraphqlError::NotAnObject),
+    };
+
+    let mut types = vec![];
+    object_type("Root", xs, &mut types);
+    Ok(
+      types
+        .iter()
+        .map(|(name, fields)| {
+          let fields = fields
+            .iter()
+            .map(|(key, t)| format!("  {}: {}\n", key, t))
+            .collect::<String>();
+          format!("type {} {{\n{}}}", name, fields)
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n"),
+    )
+  }
+}
+
+/// Adds the type of an object to `types` under a unique name based on
+/// `name`, followed by the types of its nested objects, and returns the
+/// name.
+fn object_type(name: &str, xs: &[(Cow<str>, Node)], types: &mut Vec<(String, Fields)>) -> String {
+  let unique = unique_name(name, types.iter().map(|(x, _)| x));
+  let index = types.len();
+  types.push((unique.clone(), vec![]));
+  let mut fields: Fields = vec![];
+  for (key, x) in xs {
+    let key = unique_name(&graphql_name(unquote(key)), fields.iter().map(|(x, _)| x));
+    let t = field_type(&key, x, types);
+    fields.push((key, t));
+  }
+  types[index].1 = fields;
+  unique
+}
+
+/// Returns `name`, or `name` with the lowest number suffix from 2 that is
+/// not in `taken`.
+fn unique_name<'a>(name: &str, taken: impl Iterator<Item = &'a String> + Clone) -> String {
+  let mut unique = name.to_owned();
+  let mut suffix = 1;
+  while taken.clone().any(|x| *x == unique) {
+    suffix += 1;
+    unique = format!("{}{}", name, suffix);
+  }
+  unique
+}
+
+fn field_type(key: &str, node: &Node, types: &mut Vec<(String, Fields)>) -> String {
+  match node {
+    Value(x) => match x.as_ref() {
+      "true" | "false" => "Boolean".to_owned(),
+      "null" => "String".to_owned(),
+      x if x.starts_with('"') => "String".to_owned(),
+      x if x.contains(['.', 'e', 'E']) => "Float".to_owned(),
+      _ => "Int".to_owned(),
+    },
+    Object(xs) if xs.is_empty() => "String".to_owned(),
+    Object(xs) => object_type(&type_name(key), xs, types),
+    Array(xs) => match xs.first() {
+      Some(x) => format!("[{}]", field_type(key, x, types)),
+      None => "[String]".to_owned(),
+    },
+  }
+}
+
+/// Replaces the characters GraphQL does not allow in names with `_`.
+fn graphql_name(key: &str) -> String {
+  let name = key
+    .chars()
+    .map(|x| match x.is_ascii_alphanumeric() {
+      true => x,
+      false => '_',
+    })
+    .collect::<String>();
+  match name.starts_with(|x: char| x.is_ascii_digit()) || name.is_empty() {
+    true => format!("_{}", name),
+    false => name,
+  }
+}
+
+/// Converts a field name like `home_address` to a type name like
+/// `HomeAddress`.
+fn type_name(key: &str) -> String {
+  let name = key
+    .split('_')
+    .filter(|x| !x.is_empty())
+    .map(|x| {
+      let mut chars = x.chars();
+      chars.next().map_or(String::new(), |c| {
+        c.to_ascii_uppercase().to_string() + chars.as_str()
+      })
+    })
+    .collect::<String>();
+  match name.starts_with(|x: char| x.is_ascii_alphabetic()) {
+    true => name,
+    false => format!("Type{}", name),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::GraphqlError;
+  use crate::parse::parse;
+
+  #[test]
+  fn to_graphql_schema() {
+    let tests = vec![
+      ("{}", Err(GraphqlError::EmptyObject)),
+      (
+        r#"{"a-b":1,"a_b":"x","a_b2":true,"e":{},"f":[{}]}"#,
+        Ok("type Root {\n  a_b: Int\n  a_b2: String\n  a_b22: Boolean\n  e: String\n  f: [String]\n}"),
+      ),
+      (
+        r#"{"id":1,"name":"foo","tags":["a","b"]}"#,
+        Ok("type Root {\n  id: Int\n  name: String\n  tags: [String]\n}"),
+      ),
+      (
+        r#"{"score":1.5,"ok":true,"x":null,"empty":[],"1st":2,"a-b":3}"#,
+        Ok(
+          "type Root {\n  score: Float\n  ok: Boolean\n  x: String\n  empty: [String]\n  _1st: Int\n  a_b: Int\n}",
+        ),
+      ),
+      (
+        r#"{"home_address":{"city":"x","geo":{"lat":1.0}},"items":[{"id":1}],"geo":{"id":2}}"#,
+        Ok(
+          "type Root {\n  home_address: HomeAddress\n  items: [Items]\n  geo: Geo2\n}\n\n\
+           type HomeAddress {\n  city: String\n  geo: Geo\n}\n\n\
+           type Geo {\n  lat: Float\n}\n\n\
+           type Items {\n  id: Int\n}\n\n\
+           type Geo2 {\n  id: Int\n}",
+        ),
+      ),
+      ("[]", Err(GraphqlError::NotAnObject)),
+      ("1", Err(GraphqlError::NotAnObject)),
+    ];
+
+    for (input, expected) in tests {
+      assert_eq!(
+        parse(input).unwrap().to_graphql_schema(),
+        expected.map(str::to_owned),
+        "input: {}",
+        input
+      );
+    }
+  }
+}
@@ -0,0 +1,19 @@
+//! Sorting and formatting of JSON documents, the library behind the
+//! `jsonsrt` command.
+
+pub mod config;
+pub mod export;
+pub mod file;
+pub mod format;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+pub mod node;
+pub mod parse;
+pub mod sort;
+pub mod transform;
+pub mod validate;
+
+pub use format::FormatOptions;
+pub use node::{Node, OwnedNode};
+pub use parse::parse;
+pub use sort::SortOptions;
@@ -1,181 +1,2271 @@
-use clap::Parser;
-use parse::parse;
+use clap::{error::ErrorKind, CommandFactory, Parser};
+use jsonsrt::{
+  config::load_sort_order_from_editorconfig,
+  export::CsvOptions,
+  file::write_atomically,
+  node::JsonType,
+  parse::{parse, parse_with_options, ParseError, ParseOptions},
+  sort::{
+    normalize_literal, unescape_value, unquote, MissingKeyPosition, SortKeyPipeline, SortMode,
+  },
+  transform::KeyCase,
+  validate::NamingConvention,
+  FormatOptions, Node, SortOptions,
+};
+#[cfg(feature = "random")]
+use rand::{rngs::StdRng, SeedableRng};
+use similar::{udiff::unified_diff, Algorithm};
 use std::{
+  cell::{Cell, RefCell},
+  cmp::Ordering,
+  collections::HashMap,
+  fmt::Display,
   fs,
-  io::{self, Read},
-  process::exit,
+  io::{self, Read, Write},
+  path::{Path, PathBuf},
+  process::{exit, Child, Command, Stdio},
+  thread,
+  time::{Duration, Instant},
 };
 
-mod format;
-mod node;
-mod parse;
-mod sort;
-
 /// Sort JSON contents
 #[derive(Debug, Parser, PartialEq)]
 #[command(version)]
 struct Args {
+  #[command(flatten)]
+  sort: SortArgs,
+
+  #[command(flatten)]
+  input: InputArgs,
+
+  #[command(flatten)]
+  validate: ValidateArgs,
+
+  #[command(flatten)]
+  transform: TransformArgs,
+
+  #[command(flatten)]
+  format: FormatArgs,
+
+  #[command(flatten)]
+  export: ExportArgs,
+
+  #[command(flatten)]
+  write: WriteArgs,
+
+  /// Files to process in place, otherwise uses stdin/stdout
+  files: Vec<PathBuf>,
+}
+
+/// Options choosing how to sort.
+#[derive(Debug, clap::Args, PartialEq)]
+#[command(next_help_heading = "Sorting")]
+struct SortArgs {
   /// Sort objects by key names
   #[arg(long)]
   sort_by_name: bool,
 
-  /// Sort object arrays by comparing the values of KEY
+  /// Do not sort the keys listed by json_key_order in .editorconfig next to each file first
+  #[arg(long)]
+  no_editorconfig: bool,
+
+  /// Sort objects by name, except for keys listed in order in an array under the key K, like
+  /// "__order", which is removed
+  #[arg(long, value_name = "K")]
+  order_hint_key: Option<String>,
+
+  /// Sort objects by lowercased key names
+  #[arg(long)]
+  sort_lowercase_keys: bool,
+
+  /// Sort objects by the output of the shell command CMD given each key name as input
+  #[arg(long, value_name = "CMD")]
+  key_transform: Option<String>,
+
+  /// Sort objects by running the shell command CMD with two key names as input, expecting <, =
+  /// or > as output
+  #[arg(long, value_name = "CMD")]
+  sort_with_external_cmp: Option<String>,
+
+  /// Sort by name instead if a run of --sort-with-external-cmp takes longer than MS milliseconds
+  #[arg(long, value_name = "MS", requires = "sort_with_external_cmp")]
+  cmp_timeout: Option<u64>,
+
+  /// Sort keys spelling null, true or false in any case as if in lower case,
+  /// implied by --sort-lowercase-keys
+  #[arg(long)]
+  sort_normalize_literals: bool,
+
+  /// Shuffle object keys into a random order instead of sorting them
+  #[cfg(feature = "random")]
+  #[arg(long)]
+  randomize: bool,
+
+  /// Seed for --randomize, a random seed is used and printed to stderr if not given
+  #[cfg(feature = "random")]
+  #[arg(long, value_name = "N", requires = "randomize")]
+  seed: Option<u64>,
+
+  /// Sort objects by the hash of their key names, deterministic but not readable
+  #[cfg(feature = "xxhash")]
+  #[arg(long)]
+  sort_hash_order: bool,
+
+  /// Sort objects by key names using the collation rules of a BCP 47 locale, like de-DE
+  #[cfg(feature = "icu")]
+  #[arg(long, value_name = "LOCALE")]
+  sort_locale: Option<String>,
+
+  /// Sort objects by key names, alternating ascending and descending by depth
+  #[arg(long)]
+  sort_alternating: bool,
+
+  /// Sort object arrays among their sibling keys by the keys of their first object
+  #[arg(long)]
+  sort_arrays_as_block: bool,
+
+  /// Sort arrays of strings, numbers, booleans and nulls, numbers by value
+  #[arg(long)]
+  sort_arrays: bool,
+
+  /// Sort object arrays by comparing the values of KEY, a comma separated list of keys
+  /// sorts by the first and breaks ties with the rest, and a.b looks up b in a nested object
   #[arg(long, value_name = "KEY")]
   sort_by_value: Option<String>,
 
-  /// File to process, otherwise uses stdin/stdout
-  file: Option<String>,
+  /// Reverse the order of --sort-by-name and --sort-by-value
+  #[arg(long)]
+  reverse: bool,
+
+  /// Compare --sort-by-value values as versions like v1.2.3
+  #[arg(long, requires = "sort_by_value")]
+  sort_version_string: bool,
+
+  /// Put objects without the --sort-by-value key after the others
+  #[arg(long, requires = "sort_by_value", conflicts_with = "missing_key_first")]
+  missing_key_last: bool,
+
+  /// Put objects without the --sort-by-value key before the others
+  #[arg(long, requires = "sort_by_value")]
+  missing_key_first: bool,
+
+  /// Ignore case when comparing key names for --sort-by-name
+  #[arg(long)]
+  case_insensitive: bool,
+
+  /// Sort objects by key names and object arrays by the values of KEY
+  #[arg(long, value_name = "KEY")]
+  sort_all: Option<String>,
+
+  /// Sort objects by name, then object arrays by their key names and the values of KEY
+  #[arg(long, value_name = "KEY")]
+  sort_secondary_by_value: Option<String>,
+
+  /// Sort object arrays by the values of KEY, grouping them by JSON type first
+  #[arg(long, value_name = "KEY")]
+  sort_typed_values: Option<String>,
+
+  /// Check that sorting by name gives the same output for differently ordered keys
+  #[arg(long)]
+  verify_sort: bool,
+
+  /// Number of runs for --verify-sort
+  #[arg(long, value_name = "N", default_value_t = 10, requires = "verify_sort")]
+  verify_runs: usize,
+
+  /// Print the keys moved by --sort-by-name to stderr
+  #[arg(long, requires = "sort_by_name")]
+  explain_sort: bool,
+
+  /// Leave files untouched if their keys are already sorted by name and no other changes are asked for
+  #[arg(long, requires = "sort_by_name")]
+  idempotent: bool,
+
+  /// After sorting, write the key order of the top-level objects to
+  /// <file>.sort_order.json, or to stderr when reading stdin
+  #[arg(long)]
+  debug_sort_order: bool,
+}
+
+/// Options choosing how to read the input.
+#[derive(Debug, clap::Args, PartialEq)]
+#[command(next_help_heading = "Input")]
+struct InputArgs {
+  /// Accept a trailing comma after the last element of arrays and objects, and unquoted keys
+  #[arg(long)]
+  lenient: bool,
+
+  /// Accept and drop // and /* */ comments, as in JSONC files
+  #[arg(long)]
+  jsonc: bool,
+
+  /// Treat the input as JSON Lines, sorting and compacting each line separately
+  #[arg(long)]
+  jsonl: bool,
+
+  /// Read the input from the environment variables named PREFIX_*, instead of files or stdin
+  #[arg(long, value_name = "PREFIX", conflicts_with = "files")]
+  from_env: Option<String>,
+
+  /// Read the input as MessagePack instead of JSON
+  #[cfg(feature = "msgpack")]
+  #[arg(long)]
+  from_msgpack: bool,
+}
+
+/// Options checking the input.
+#[derive(Debug, clap::Args, PartialEq)]
+#[command(next_help_heading = "Validation")]
+struct ValidateArgs {
+  /// Fail if any object key does not follow CONVENTION
+  #[arg(long, value_name = "CONVENTION")]
+  validate_convention: Option<NamingConvention>,
+
+  /// Fail if any number is out of range for, or too precise for, a 64-bit float
+  #[arg(long)]
+  validate_numbers: bool,
+
+  /// Warn about strings that escape a character in more than one way
+  #[arg(long)]
+  warn_mixed_escapes: bool,
+}
+
+/// Options changing the content of the input.
+#[derive(Debug, clap::Args, PartialEq)]
+#[command(next_help_heading = "Transforms")]
+struct TransformArgs {
+  /// Merge nested objects into the top-level object, joining keys with SEP
+  #[arg(long, value_name = "SEP")]
+  flatten_objects: Option<char>,
+
+  /// Replace arrays inside arrays with their elements
+  #[arg(long)]
+  flatten_arrays: bool,
+
+  /// Number of levels of nested arrays to flatten for --flatten-arrays, all by default
+  #[arg(long, value_name = "N", requires = "flatten_arrays")]
+  flatten_depth: Option<usize>,
+
+  /// Nest the values of dotted top-level keys, the inverse of --flatten-objects
+  #[arg(long)]
+  expand_keys: bool,
+
+  /// Rename all object keys to CASE
+  #[arg(long, value_name = "CASE")]
+  rename_keys: Option<KeyCase>,
+
+  /// Remove the entries with the comma separated keys KEYS from all objects
+  #[arg(long, value_name = "KEYS", value_delimiter = ',')]
+  exclude_keys: Vec<String>,
+
+  /// Keep only the entries with the comma separated keys KEYS in all objects
+  #[arg(long, value_name = "KEYS", value_delimiter = ',')]
+  only_keys: Vec<String>,
+
+  /// Trim leading and trailing whitespace from string values
+  #[arg(long)]
+  trim_strings: bool,
+
+  /// Merge the object in FILE into the input object, replacing the values of matching keys
+  #[arg(long, value_name = "FILE")]
+  merge: Option<String>,
+
+  /// Append the elements of the array in FILE to the input array
+  #[arg(long, value_name = "FILE")]
+  concat: Option<String>,
+}
+
+/// Options choosing how to write the output.
+#[derive(Debug, clap::Args, PartialEq)]
+#[command(next_help_heading = "Formatting")]
+struct FormatArgs {
+  /// Write the output on a single line without spaces
+  #[arg(long)]
+  compact: bool,
+
+  /// Write arrays and objects on a single line when they are short enough
+  #[arg(long)]
+  auto_format: bool,
+
+  /// Maximum length of a single line array or object for --auto-format
+  #[arg(long, value_name = "N", default_value_t = 80, requires = "auto_format")]
+  auto_format_threshold: usize,
+
+  /// Write arrays and objects holding only values on a single line, and the others on multiple
+  /// lines
+  #[arg(long)]
+  elm_style: bool,
+
+  /// Indent with N spaces per level [default: 2]
+  #[arg(long, value_name = "N")]
+  indent: Option<usize>,
+
+  /// Indent with tabs instead of spaces
+  #[arg(long, conflicts_with = "indent")]
+  tab: bool,
+
+  /// Omit the space after colons in objects
+  #[arg(long)]
+  compact_colons: bool,
+
+  /// Sort objects in the order of the properties of the JSON Schema in SCHEMA_FILE
+  #[arg(long, value_name = "SCHEMA_FILE")]
+  format_with_schema: Option<String>,
+
+  /// Put the description or title of each property in SCHEMA_FILE before
+  /// its entries as a comment, producing invalid JSON
+  #[arg(long, value_name = "SCHEMA_FILE")]
+  schema_docs: Option<String>,
+
+  /// Line up the values of each object in one column
+  #[arg(long)]
+  align_values: bool,
+
+  /// Right align the numbers of each object with its longest value
+  #[arg(long)]
+  align_numbers: bool,
+
+  /// Put an empty line between object entries
+  #[arg(long)]
+  blank_lines: bool,
+
+  /// Precede each object entry with a comment of its JSON Pointer, producing invalid JSON
+  #[arg(long)]
+  path_comments: bool,
+
+  /// Replace strings longer than N characters with a placeholder, losing data
+  #[arg(long, value_name = "N", conflicts_with = "files")]
+  display_truncate: Option<usize>,
+
+  /// End the output with a // comment counting the objects, arrays and values, and the depth
+  #[arg(long, conflicts_with_all = ["format_with_schema", "schema_docs"])]
+  stats_footer: bool,
+
+  /// Write \n and \t in strings as actual newlines and tabs, the output is no longer valid JSON
+  #[arg(long, conflicts_with = "files")]
+  display_unescape: bool,
+
+  /// Write numbers with an integer value, like 1.0 or 1e2, as integers
+  #[arg(long)]
+  normalize_integers: bool,
+
+  /// Write integers of more than 15 digits as strings, for JavaScript
+  #[arg(long, conflicts_with = "unquote_bigints")]
+  bigint_as_string: bool,
+
+  /// Write strings holding integers of more than 15 digits as numbers
+  #[arg(long)]
+  unquote_bigints: bool,
+
+  /// Write array items on their own lines after PREFIX, like "- ", without brackets or
+  /// commas, objects keep theirs, so the output is neither JSON nor YAML
+  #[arg(long, value_name = "PREFIX")]
+  array_item_prefix: Option<String>,
+
+  /// Escape the line and paragraph separators U+2028 and U+2029 in strings
+  #[arg(long)]
+  escape_line_sep: bool,
+
+  /// Write keys under the names given by the JSON object in FILE, like
+  /// {"usr_nm": "user_name"}
+  #[arg(long, value_name = "FILE")]
+  alias_keys_file: Option<String>,
+
+  /// Write the values of the comma separated keys KEYS as "***"
+  #[arg(long, value_name = "KEYS", value_delimiter = ',')]
+  mask_keys: Vec<String>,
+}
+
+/// Options writing the input in other formats instead.
+#[derive(Debug, clap::Args, PartialEq)]
+#[command(next_help_heading = "Export")]
+struct ExportArgs {
+  /// Print TEMPLATE with {{KEY}} placeholders replaced by values from the input
+  #[arg(long, value_name = "TEMPLATE")]
+  template: Option<String>,
+
+  /// Print an array of objects converted to CSV
+  #[arg(long)]
+  to_csv: bool,
+
+  /// Separate CSV fields with CHAR, e.g. ';' or '\t' [default: ,]
+  #[arg(long, value_name = "CHAR", value_parser = parse_char, requires = "to_csv")]
+  csv_delimiter: Option<char>,
+
+  /// Omit the CSV header row
+  #[arg(long, requires = "to_csv")]
+  csv_no_header: bool,
+
+  /// Write VALUE for null values in CSV [default: empty]
+  #[arg(long, value_name = "VALUE", requires = "to_csv")]
+  csv_null_value: Option<String>,
+
+  /// Print the input converted to XML
+  #[cfg(feature = "xml")]
+  #[arg(long)]
+  to_xml: bool,
+
+  /// Name of the root element for --to-xml [default: root]
+  #[cfg(feature = "xml")]
+  #[arg(long, value_name = "NAME", requires = "to_xml")]
+  xml_root_element: Option<String>,
+
+  /// Print an object as .env lines, with names starting with PREFIX
+  #[arg(long, value_name = "PREFIX")]
+  to_dotenv: Option<String>,
+
+  /// Print GraphQL type definitions inferred from the input object
+  #[cfg(feature = "graphql")]
+  #[arg(long)]
+  to_graphql: bool,
+
+  /// Print the JSON Pointer and value of every leaf of type TYPE
+  #[arg(long, value_name = "TYPE")]
+  extract_type: Option<JsonType>,
+
+  /// Write the output as MessagePack to FILE
+  #[cfg(feature = "msgpack")]
+  #[arg(long, value_name = "FILE")]
+  to_msgpack: Option<String>,
+
+  /// Also write the input sorted by name to PATH, e.g. a canonical JSON
+  /// Schema, for at most one input file
+  #[arg(long, value_name = "PATH")]
+  emit_schema: Option<String>,
+}
+
+/// Options choosing what to do with the output.
+#[derive(Debug, clap::Args, PartialEq)]
+#[command(next_help_heading = "Writing")]
+struct WriteArgs {
+  /// Exit with an error instead of writing if the output differs from the input
+  #[arg(long)]
+  check: bool,
+
+  /// Print a unified diff of the changes instead of writing, and exit with an error if
+  /// there are any
+  #[arg(long, conflicts_with = "check")]
+  diff: bool,
+
+  /// Print an estimate of the output size in characters to stderr instead of writing
+  #[arg(long)]
+  dry_run_size: bool,
+
+  /// Copy each file to the file name plus SUFFIX, .bak by default, before overwriting it
+  #[arg(
+    long,
+    value_name = "SUFFIX",
+    num_args = 0..=1,
+    require_equals = true,
+    default_missing_value = ".bak"
+  )]
+  backup: Option<String>,
+}
+
+impl SortArgs {
+  /// The options for the library sorts, with `priority` keys first when
+  /// sorting by name.
+  fn options(&self, priority: Vec<String>) -> SortOptions {
+    SortOptions {
+      reverse: self.reverse,
+      case_insensitive: self.case_insensitive,
+      mode: match self.sort_version_string {
+        true => SortMode::VersionString,
+        false => SortMode::Natural,
+      },
+      missing_key: match (self.missing_key_first, self.missing_key_last) {
+        (true, _) => MissingKeyPosition::First,
+        (_, true) => MissingKeyPosition::Last,
+        _ => MissingKeyPosition::Unspecified,
+      },
+      priority,
+    }
+  }
+}
+
+impl FormatArgs {
+  fn options(&self, key_aliases: HashMap<String, String>) -> FormatOptions {
+    let defaults = FormatOptions::default();
+    FormatOptions {
+      compact: self.compact,
+      compact_below: self.auto_format.then_some(self.auto_format_threshold),
+      elm_style: self.elm_style,
+      indent: match (self.tab, self.indent) {
+        (true, _) => "\t".to_owned(),
+        (false, Some(n)) => " ".repeat(n),
+        (false, None) => defaults.indent,
+      },
+      space_after_colon: !self.compact_colons,
+      align_values: self.align_values,
+      align_numbers: self.align_numbers,
+      blank_line_between_entries: self.blank_lines,
+      path_comments: self.path_comments,
+      max_string_length_display: self.display_truncate,
+      unescape_string_newlines: self.display_unescape,
+      escape_line_separators: self.escape_line_sep,
+      normalize_integer_floats: self.normalize_integers,
+      bigint_as_string: self.bigint_as_string,
+      unquote_bigints: self.unquote_bigints,
+      object_trailing_newline: false,
+      array_item_prefix: self.array_item_prefix.clone(),
+      key_aliases,
+      mask_keys: self.mask_keys.clone(),
+    }
+  }
 }
 
 fn main() -> io::Result<()> {
   let args = Args::parse();
-  match parse(&read_input(&args)?) {
-    Err(e) => {
-      eprintln!("{}", e);
-      exit(1);
+  if args.export.emit_schema.is_some() && args.files.len() > 1 {
+    Args::command()
+      .error(
+        ErrorKind::ArgumentConflict,
+        "--emit-schema cannot be used with more than one file",
+      )
+      .exit();
+  }
+  let schema_input = read_option_file(args.format.format_with_schema.as_ref())?;
+  let docs_input = read_option_file(args.format.schema_docs.as_ref())?;
+  let concat_input = read_option_file(args.transform.concat.as_ref())?;
+  let merge_input = read_option_file(args.transform.merge.as_ref())?;
+  let key_aliases = match args.format.alias_keys_file.as_ref() {
+    Some(path) => load_key_aliases(&fs::read_to_string(path)?),
+    None => HashMap::new(),
+  };
+  let context = Context {
+    schema: schema_input.as_deref().map(parse_or_exit),
+    docs: docs_input.as_deref().map(parse_or_exit),
+    concat: concat_input.as_deref().map(parse_or_exit),
+    merge: merge_input.as_deref().map(parse_or_exit),
+    format: args.format.options(key_aliases),
+    changed: Cell::new(false),
+  };
+
+  if args.format.display_unescape {
+    eprintln!("warning: --display-unescape output is not valid JSON");
+  }
+
+  let process_file = |file| process(&args, file, &context);
+
+  if args.files.is_empty() {
+    return match process_file(None) {
+      Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+        eprintln!("{}", e);
+        exit(1);
+      }
+      Ok(()) if context.changed.get() => exit(1),
+      result => result,
+    };
+  }
+
+  // Keep going after a file fails, so one bad file does not leave the
+  // rest unprocessed.
+  let mut failed = false;
+  for file in &args.files {
+    if let Err(e) = process_file(Some(file)) {
+      eprintln!("{}: {}", file.display(), e);
+      failed = true;
+    }
+  }
+  if failed || context.changed.get() {
+    exit(1);
+  }
+  Ok(())
+}
+
+/// What `process` needs besides `Args`, prepared once for all files.
+struct Context<'a> {
+  /// The documents read from the files given by options.
+  schema: Option<Node<'a>>,
+  docs: Option<Node<'a>>,
+  concat: Option<Node<'a>>,
+  merge: Option<Node<'a>>,
+  format: FormatOptions,
+  /// Set when `--check` or `--diff` finds changes.
+  changed: Cell<bool>,
+}
+
+/// Processes one file in place, or stdin to stdout if `file` is `None`.
+/// Input that does not parse or fails a validation gives an `InvalidData`
+/// error.
+fn process(args: &Args, file: Option<&Path>, context: &Context) -> io::Result<()> {
+  let input = read_input(args, file)?;
+  if args.input.jsonl {
+    let output = process_lines(args, file, &input);
+    return finish(args, file, &input, &output, &context.changed);
+  }
+
+  match parse_input(args, &input) {
+    Err(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+
+    Ok(mut node) => {
+      if args.sort.idempotent
+        && file.is_some()
+        && only_sorts_by_name(args)
+        && editorconfig_order(args, file).is_none()
+        && node.is_sorted_by_name()
+      {
+        return Ok(());
+      }
+
+      if let Some(convention) = args.validate.validate_convention {
+        invalid_if_any(node.validate_key_naming_convention(convention))?;
+      }
+
+      if args.validate.validate_numbers {
+        invalid_if_any(node.validate_no_number_overflow())?;
+      }
+
+      if args.validate.warn_mixed_escapes {
+        node
+          .detect_encoding_inconsistencies()
+          .iter()
+          .for_each(|path| eprintln!("warning: {}: string mixes escape styles", path));
+      }
+
+      if let Some(path) = args.export.emit_schema.as_ref() {
+        node.write_sorted_to_file(Path::new(path), &SortOptions::default())?;
+      }
+
+      if let Some(separator) = args.transform.flatten_objects {
+        node.flatten_nested_objects_to_top(separator);
+      }
+
+      if args.transform.flatten_arrays {
+        node.flatten_array_values(args.transform.flatten_depth.unwrap_or(usize::MAX));
+      }
+
+      if args.transform.expand_keys {
+        node.expand_dot_notation_keys();
+      }
+
+      if let Some(case) = args.transform.rename_keys {
+        node = node.map_keys(&|x| case.apply(x));
+      }
+
+      if !args.transform.exclude_keys.is_empty() {
+        node.retain_keys(&|key| !args.transform.exclude_keys.iter().any(|x| x == key));
+      }
+
+      if !args.transform.only_keys.is_empty() {
+        node.retain_keys(&|key| args.transform.only_keys.iter().any(|x| x == key));
+      }
+
+      if args.transform.trim_strings {
+        node.strip_whitespace_from_values();
+      }
+
+      if let Some(other) = context.merge.as_ref() {
+        node = node.merge(other);
+      }
+
+      if let Some(other) = context.concat.as_ref() {
+        node.merge_array_concat(other);
+      }
+
+      if args.sort.verify_sort {
+        node
+          .verify_sort_by_name(args.sort.verify_runs)
+          .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+      }
+
+      sort(args, file, &mut node);
+
+      if args.sort.debug_sort_order {
+        let order = Node::Array(
+          node
+            .emit_key_order()
+            .into_iter()
+            .map(|keys| Node::Array(keys.into_iter().map(Node::Value).collect()))
+            .collect(),
+        );
+        match file {
+          Some(path) => {
+            let mut path = path.as_os_str().to_owned();
+            path.push(".sort_order.json");
+            fs::write(path, order.to_string() + "\n")?
+          }
+          None => eprintln!("{}", order),
+        }
+      }
+
+      if let Some(t) = args.export.extract_type {
+        let values = node
+          .extract_values_by_type(t)
+          .into_iter()
+          .map(|(path, value)| {
+            Node::Object(vec![
+              (
+                "\"path\"".into(),
+                Node::Value(format!("\"{}\"", path).into()),
+              ),
+              ("\"value\"".into(), Node::Value(value.into())),
+            ])
+          })
+          .collect();
+        println!("{}", Node::Array(values));
+        return Ok(());
+      }
+
+      if let Some(template) = args.export.template.as_ref() {
+        return print_or_exit(node.to_template_string(template));
+      }
+
+      if args.export.to_csv {
+        let defaults = CsvOptions::default();
+        return print_or_exit(
+          node.to_csv_with_options(&CsvOptions {
+            delimiter: args.export.csv_delimiter.unwrap_or(defaults.delimiter),
+            null_value: args
+              .export
+              .csv_null_value
+              .clone()
+              .unwrap_or(defaults.null_value),
+            header: !args.export.csv_no_header,
+            ..defaults
+          }),
+        );
+      }
+
+      if let Some(prefix) = args.export.to_dotenv.as_ref() {
+        return print_or_exit(node.to_dotenv_string(prefix));
+      }
+
+      #[cfg(feature = "graphql")]
+      if args.export.to_graphql {
+        return print_or_exit(node.to_graphql_schema());
+      }
+
+      #[cfg(feature = "msgpack")]
+      if let Some(path) = args.export.to_msgpack.as_ref() {
+        return fs::write(path, node.to_msgpack());
+      }
+
+      #[cfg(feature = "xml")]
+      if args.export.to_xml {
+        return print_or_exit(match args.export.xml_root_element.as_ref() {
+          Some(root) => node.to_xml_with_root(root),
+          None => node.to_xml(),
+        });
+      }
+
+      if args.write.dry_run_size {
+        eprintln!("{}", node.count_total_characters());
+        return Ok(());
+      }
+
+      let options = &context.format;
+      let mut output = match (context.schema.as_ref(), context.docs.as_ref()) {
+        (Some(schema), None) => node.to_sorted_with_schema_order(schema, options),
+        (schema, Some(docs)) => {
+          if let Some(schema) = schema {
+            node.sort_by_schema(schema);
+          }
+          node.to_string_with_schema_docs(docs, options)
+        }
+        (None, None) if args.format.stats_footer => node.to_string_with_statistics_footer(options),
+        (None, None) => node.to_string_with_options(options),
+      };
+      output.push('\n');
+
+      finish(args, file, &input, &output, &context.changed)
     }
+  }
+}
+
+/// Whether `args` asks for nothing but sorting by name, so a file whose
+/// keys are already sorted can be left as it is. Any option other than the
+/// `WriteArgs` and `--no-editorconfig` may change the output.
+fn only_sorts_by_name(args: &Args) -> bool {
+  let plain = Args::parse_from(["jsonsrt", "--sort-by-name", "--idempotent"]);
+  args.sort
+    == SortArgs {
+      no_editorconfig: args.sort.no_editorconfig,
+      ..plain.sort
+    }
+    && args.input == plain.input
+    && args.validate == plain.validate
+    && args.transform == plain.transform
+    && args.format == plain.format
+    && args.export == plain.export
+}
+
+/// Checks, diffs or writes the formatted `output` of `input`, as selected by
+/// `args`.
+fn finish(
+  args: &Args,
+  file: Option<&Path>,
+  input: &[u8],
+  output: &str,
+  changed: &Cell<bool>,
+) -> io::Result<()> {
+  if args.write.check {
+    if output.as_bytes() != input {
+      let name = file.map_or("<stdin>".into(), |x| x.display().to_string());
+      eprintln!("{}: would be reformatted", name);
+      changed.set(true);
+    }
+    return Ok(());
+  }
+
+  if args.write.diff {
+    let old = String::from_utf8_lossy(input);
+    if old != output {
+      let name = file.map_or("<stdin>".into(), |x| x.display().to_string());
+      let (a, b) = (format!("a/{}", name), format!("b/{}", name));
+      print!(
+        "{}",
+        unified_diff(Algorithm::Myers, &old, output, 3, Some((&a, &b)))
+      );
+      changed.set(true);
+    }
+    return Ok(());
+  }
+
+  write_output(file, args.write.backup.as_deref(), output)
+}
+
+/// Sorts and compacts each line of newline delimited JSON, keeping lines that
+/// fail to parse as they are.
+fn process_lines(args: &Args, file: Option<&Path>, input: &[u8]) -> String {
+  let mut output = String::new();
+  for (i, line) in String::from_utf8_lossy(input).lines().enumerate() {
+    if line.trim().is_empty() {
+      continue;
+    }
+    match parse_str(args, line) {
+      Ok(mut node) => {
+        sort(args, file, &mut node);
+        output.push_str(&node.to_compact_string());
+      }
+      Err(e) => {
+        eprintln!("warning: line {}: {}", i + 1, e);
+        output.push_str(line);
+      }
+    }
+    output.push('\n');
+  }
+  output
+}
+
+/// Applies the sorts selected by `args` to `node`.
+fn sort(args: &Args, file: Option<&Path>, node: &mut Node) {
+  let sort_options = args.sort.options(match args.sort.sort_by_name {
+    true => editorconfig_order(args, file).unwrap_or_default(),
+    false => vec![],
+  });
+
+  if args.sort.sort_by_name {
+    match args.sort.explain_sort {
+      true => node
+        .sort_by_name_with_diff(&sort_options)
+        .iter()
+        .for_each(|x| eprintln!("{}", x)),
+      false => node.sort_by_name(&sort_options),
+    }
+  }
+
+  if let Some(key) = args.sort.order_hint_key.as_ref() {
+    node.sort_by_name_with_order_hint(key);
+  }
+
+  if args.sort.sort_lowercase_keys {
+    node.sort_by_name_piped(
+      &SortKeyPipeline::new()
+        .then(|x| unquote(x).to_owned())
+        .then(str::to_lowercase),
+    );
+  } else if args.sort.sort_normalize_literals {
+    node.sort_by_name_piped(
+      &SortKeyPipeline::new()
+        .then(|x| unquote(x).to_owned())
+        .then(normalize_literal),
+    );
+  }
+
+  if let Some(command) = args.sort.key_transform.clone() {
+    // Run the command once per distinct key, not once per object.
+    let cache = RefCell::new(HashMap::new());
+    node.sort_by_name_piped(&SortKeyPipeline::new().then(move |key| {
+      let mut cache = cache.borrow_mut();
+      let transformed = cache.entry(key.to_owned()).or_insert_with(|| {
+        transform_key(&command, unquote(key)).unwrap_or_else(|e| {
+          eprintln!("{}", e);
+          exit(1);
+        })
+      });
+      transformed.clone()
+    }));
+  }
+
+  if let Some(command) = args.sort.sort_with_external_cmp.as_ref() {
+    let timeout = args.sort.cmp_timeout.map(Duration::from_millis);
+    let cache = RefCell::new(HashMap::new());
+    let timed_out = Cell::new(false);
+    node.sort_by_name_with_comparator(&|a, b| {
+      if timed_out.get() {
+        return Ordering::Equal;
+      }
+      let mut cache = cache.borrow_mut();
+      let key = (a.to_owned(), b.to_owned());
+      *cache
+        .entry(key)
+        .or_insert_with(|| match compare_keys(command, a, b, timeout) {
+          Ok(Some(x)) => x,
+          Ok(None) => {
+            timed_out.set(true);
+            Ordering::Equal
+          }
+          Err(e) => {
+            eprintln!("{}", e);
+            exit(1);
+          }
+        })
+    });
+    if timed_out.get() {
+      eprintln!("warning: {}: timed out, sorting by name instead", command);
+      node.sort_by_name(&sort_options);
+    }
+  }
+
+  if args.sort.sort_alternating {
+    node.sort_by_name_alternating(0);
+  }
+
+  if args.sort.sort_arrays_as_block {
+    node.sort_by_name_arrays_as_block();
+  }
+
+  if args.sort.sort_arrays {
+    node.sort_arrays();
+  }
+
+  #[cfg(feature = "random")]
+  if args.sort.randomize {
+    let seed = args.sort.seed.unwrap_or_else(|| {
+      let seed = rand::random();
+      eprintln!("seed: {}", seed);
+      seed
+    });
+    node.shuffle_keys(&mut StdRng::seed_from_u64(seed));
+  }
+
+  #[cfg(feature = "xxhash")]
+  if args.sort.sort_hash_order {
+    node.sort_by_name_hash_order();
+  }
+
+  #[cfg(feature = "icu")]
+  if let Some(locale) = args.sort.sort_locale.as_ref() {
+    if let Err(e) = node.sort_by_name_with_locale(locale) {
+      eprintln!("{}", e);
+      exit(1);
+    }
+  }
+
+  if let Some(names) = args.sort.sort_by_value.as_ref() {
+    let paths = names
+      .split(',')
+      .map(|x| x.split('.').collect::<Vec<_>>())
+      .collect::<Vec<_>>();
+    node.sort_by_values(
+      &paths.iter().map(Vec::as_slice).collect::<Vec<_>>(),
+      &sort_options,
+    );
+  }
+
+  if let Some(name) = args.sort.sort_all.as_ref() {
+    node.sort_by_value_and_name(name);
+  }
+
+  if let Some(name) = args.sort.sort_secondary_by_value.as_ref() {
+    node.sort_by_name_then_value(name);
+  }
+
+  if let Some(name) = args.sort.sort_typed_values.as_ref() {
+    node.sort_by_type_then_value(name);
+  }
+}
+
+fn read_input(args: &Args, file: Option<&Path>) -> io::Result<Vec<u8>> {
+  if args.input.from_env.is_some() {
+    Ok(vec![])
+  } else if let Some(path) = file {
+    fs::read(path)
+  } else {
+    let mut input = vec![];
+    io::stdin().read_to_end(&mut input)?;
+    Ok(input)
+  }
+}
+
+fn parse_input<'a>(args: &Args, input: &'a [u8]) -> Result<Node<'a>, String> {
+  if let Some(prefix) = args.input.from_env.as_ref() {
+    return Ok(Node::from_env_vars(prefix));
+  }
+  #[cfg(feature = "msgpack")]
+  if args.input.from_msgpack {
+    return Node::from_msgpack(input).map_err(|e| e.to_string());
+  }
+  parse_str(args, std::str::from_utf8(input).map_err(|e| e.to_string())?).map_err(|e| e.to_string())
+}
+
+fn parse_str<'a>(args: &Args, input: &'a str) -> Result<Node<'a>, ParseError> {
+  parse_with_options(
+    input,
+    ParseOptions {
+      trailing_commas: args.input.lenient,
+      comments: args.input.jsonc,
+      unquoted_keys: args.input.lenient,
+    },
+  )
+}
+
+fn editorconfig_order(args: &Args, file: Option<&Path>) -> Option<Vec<String>> {
+  let file = file?;
+  if args.sort.no_editorconfig {
+    return None;
+  }
+  load_sort_order_from_editorconfig(&file.parent()?.join(".editorconfig"))
+}
+
+/// Runs `command` with the shell, writing `key` and a newline to its stdin,
+/// and returns its output without the trailing newline.
+fn transform_key(command: &str, key: &str) -> io::Result<String> {
+  let child = spawn_with_input(command, &[key])?;
+  let output = child.wait_with_output()?;
+  if !output.status.success() {
+    return Err(io::Error::other(format!("{}: {}", command, output.status)));
+  }
+  Ok(
+    String::from_utf8_lossy(&output.stdout)
+      .trim_end_matches(['\n', '\r'])
+      .to_owned(),
+  )
+}
+
+/// Runs `command` with the shell, writing `a` and `b` on separate lines to
+/// its stdin, and returns the ordering it prints as `<`, `=` or `>`, or
+/// `None` if it is still running after `timeout`.
+fn compare_keys(
+  command: &str,
+  a: &str,
+  b: &str,
+  timeout: Option<Duration>,
+) -> io::Result<Option<Ordering>> {
+  let mut child = spawn_with_input(command, &[a, b])?;
+  if let Some(timeout) = timeout {
+    let deadline = Instant::now() + timeout;
+    while child.try_wait()?.is_none() {
+      if Instant::now() >= deadline {
+        child.kill()?;
+        child.wait()?;
+        return Ok(None);
+      }
+      thread::sleep(Duration::from_millis(1));
+    }
+  }
+  let output = child.wait_with_output()?;
+  if !output.status.success() {
+    return Err(io::Error::other(format!("{}: {}", command, output.status)));
+  }
+  match String::from_utf8_lossy(&output.stdout).trim() {
+    "<" => Ok(Some(Ordering::Less)),
+    "=" => Ok(Some(Ordering::Equal)),
+    ">" => Ok(Some(Ordering::Greater)),
+    x => Err(io::Error::other(format!(
+      "{}: expected <, = or >, got {:?}",
+      command, x
+    ))),
+  }
+}
+
+/// Starts `command` with the shell, writing each of `lines` followed by a
+/// newline to its stdin.
+fn spawn_with_input(command: &str, lines: &[&str]) -> io::Result<Child> {
+  let mut child = Command::new("sh")
+    .args(["-c", command])
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .spawn()?;
+  if let Some(mut stdin) = child.stdin.take() {
+    for line in lines {
+      stdin.write_all(line.as_bytes())?;
+      stdin.write_all(b"\n")?;
+    }
+  }
+  Ok(child)
+}
+
+fn parse_char(s: &str) -> Result<char, String> {
+  let mut chars = s.chars();
+  match (s, chars.next(), chars.next()) {
+    ("\\t", _, _) => Ok('\t'),
+    (_, Some(x), None) => Ok(x),
+    _ => Err("expected a single character".to_owned()),
+  }
+}
+
+/// Reads the file at `path`, if given.
+fn read_option_file(path: Option<&String>) -> io::Result<Option<String>> {
+  path.map(fs::read_to_string).transpose()
+}
+
+fn parse_or_exit(input: &str) -> Node<'_> {
+  parse(input).unwrap_or_else(|e| {
+    eprintln!("{}", e);
+    exit(1);
+  })
+}
+
+fn load_key_aliases(input: &str) -> HashMap<String, String> {
+  let Node::Object(xs) = parse_or_exit(input) else {
+    eprintln!("key aliases must be an object");
+    exit(1);
+  };
+  xs.iter()
+    .map(|(key, x)| match x {
+      Node::Value(x) if x.starts_with('"') => (unquote(key).to_owned(), unescape_value(unquote(x))),
+      _ => {
+        eprintln!("key alias for {} must be a string", key);
+        exit(1);
+      }
+    })
+    .collect()
+}
+
+/// Fails with an `InvalidData` error listing `errors`, one per line, if
+/// there are any.
+fn invalid_if_any(errors: Vec<impl Display>) -> io::Result<()> {
+  if errors.is_empty() {
+    return Ok(());
+  }
+  let message = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+  Err(io::Error::new(
+    io::ErrorKind::InvalidData,
+    message.join("\n"),
+  ))
+}
+
+fn print_or_exit(output: Result<String, impl Display>) -> io::Result<()> {
+  match output {
+    Ok(output) => {
+      println!("{}", output);
+      Ok(())
+    }
+    Err(e) => {
+      eprintln!("{}", e);
+      exit(1);
+    }
+  }
+}
+
+fn write_output(file: Option<&Path>, backup: Option<&str>, output: &str) -> io::Result<()> {
+  if let Some(path) = file {
+    if let Some(suffix) = backup {
+      let mut backup = path.as_os_str().to_owned();
+      backup.push(suffix);
+      fs::copy(path, backup)?;
+    }
+    write_atomically(path, output)
+  } else {
+    print!("{}", output);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{
+    error::Error,
+    fs,
+    io::{self, Write},
+    process::{Command, Output, Stdio},
+  };
+  use tempfile::NamedTempFile;
+
+  #[test]
+  fn can_use_stdin_stdout() -> io::Result<()> {
+    let mut proc = Command::new("cargo")
+      .args(["run"])
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .spawn()?;
+    proc.stdin.as_mut().unwrap().write_all(b"{ }")?;
+    let output = proc.wait_with_output()?;
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"{}\n");
+    Ok(())
+  }
+
+  #[test]
+  fn can_use_file() -> Result<(), Box<dyn Error>> {
+    let mut temp = NamedTempFile::new()?;
+    let path = temp.path().to_str().unwrap().to_owned();
+    temp.write_all(b"{ }")?;
+    temp.flush()?;
+
+    let output = Command::new("cargo")
+      .args(["run", "--quiet", "--", &path])
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .spawn()?
+      .wait_with_output()?;
+
+    assert_eq!("", String::from_utf8_lossy(&output.stdout).to_string());
+    assert_eq!("", String::from_utf8_lossy(&output.stderr).to_string());
+    assert!(output.status.success());
+    assert_eq!(&fs::read_to_string(&path)?, "{}\n");
+    Ok(())
+  }
+
+  #[test]
+  fn can_sort_by_name() -> Result<(), Box<dyn Error>> {
+    let mut temp = NamedTempFile::new()?;
+    let path = temp.path().to_str().unwrap().to_owned();
+    temp.write_all(r#"{"1":0,"0":0}"#.as_bytes())?;
+    temp.flush()?;
+
+    let output = Command::new("cargo")
+      .args(["run", "--quiet", "--", "--sort-by-name", &path])
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .spawn()?
+      .wait_with_output()?;
+
+    assert_eq!("", String::from_utf8_lossy(&output.stdout).to_string());
+    assert_eq!("", String::from_utf8_lossy(&output.stderr).to_string());
+    assert!(output.status.success());
+    assert_eq!(
+      fs::read_to_string(&path)?,
+      r#"{
+  "0": 0,
+  "1": 0
+}
+"#
+      .to_owned()
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn can_sort_stdin() -> Result<(), Box<dyn Error>> {
+    let tests: Vec<(&[&str], &str, &str)> = vec![
+      (
+        &["--compact", "--sort-by-name"],
+        r#"{"b": [1, 2, 3], "a": 1}"#,
+        "{\"a\":1,\"b\":[1,2,3]}\n",
+      ),
+      (
+        &["--order-hint-key", "_keys", "--compact"],
+        r#"{"a":1,"_keys":["c","b"],"b":2,"c":{"e":3,"d":4}}"#,
+        "{\"c\":{\"d\":4,\"e\":3},\"b\":2,\"a\":1}\n",
+      ),
+      (
+        &["--sort-all", "k"],
+        r#"{"b":[{"k":2},{"k":1}],"a":1}"#,
+        r#"{
+  "a": 1,
+  "b": [
+    {
+      "k": 1
+    },
+    {
+      "k": 2
+    }
+  ]
+}
+"#,
+      ),
+      (
+        &[
+          "--sort-by-name",
+          "--sort-by-value",
+          "k",
+          "--reverse",
+          "--compact",
+        ],
+        r#"[{"k":1,"a":1},{"k":2,"a":2}]"#,
+        "[{\"k\":2,\"a\":2},{\"k\":1,\"a\":1}]\n",
+      ),
+      (
+        &["--sort-by-name", "--case-insensitive", "--compact"],
+        r#"{"b":1,"C":{"b":1,"A":2},"a":2}"#,
+        "{\"a\":2,\"b\":1,\"C\":{\"A\":2,\"b\":1}}\n",
+      ),
+      (
+        &["--sort-by-name", "--sort-arrays", "--compact"],
+        r#"{"b":["10","9",2],"a":[{"y":1,"x":2},[3,1]]}"#,
+        "{\"a\":[{\"x\":2,\"y\":1},[1,3]],\"b\":[2,\"9\",\"10\"]}\n",
+      ),
+      (
+        &["--sort-by-value", "name,age", "--compact"],
+        r#"[{"name":"b","age":1},{"name":"a","age":30},{"name":"a","age":4}]"#,
+        "[{\"name\":\"a\",\"age\":4},{\"name\":\"a\",\"age\":30},{\"name\":\"b\",\"age\":1}]\n",
+      ),
+      (
+        &["--sort-by-value", "address.city", "--compact"],
+        r#"[{"address":{"city":"b"}},{"address":{"city":"a"}}]"#,
+        "[{\"address\":{\"city\":\"a\"}},{\"address\":{\"city\":\"b\"}}]\n",
+      ),
+      (
+        &["--sort-by-value", "k", "--missing-key-last", "--compact"],
+        r#"[{"x":1},{"k":2},{"k":1}]"#,
+        "[{\"k\":1},{\"k\":2},{\"x\":1}]\n",
+      ),
+      (
+        &["--sort-by-value", "v", "--sort-version-string", "--compact"],
+        r#"[{"v":"v1.10.0"},{"v":"v1.2.0"},{"v":"v1.2.0-rc.1"}]"#,
+        "[{\"v\":\"v1.2.0-rc.1\"},{\"v\":\"v1.2.0\"},{\"v\":\"v1.10.0\"}]\n",
+      ),
+    ];
+    for (args, input, expected) in tests {
+      let output = run(args, input)?;
+      assert_eq!(
+        "",
+        String::from_utf8_lossy(&output.stderr),
+        "args: {:?}",
+        args
+      );
+      assert!(output.status.success(), "args: {:?}", args);
+      assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        expected,
+        "args: {:?}",
+        args
+      );
+    }
+    Ok(())
+  }
+
+  #[test]
+  fn can_format_stdin() -> Result<(), Box<dyn Error>> {
+    let tests: Vec<(&[&str], &str, &str)> = vec![
+      (
+        &["--auto-format", "--auto-format-threshold", "10"],
+        r#"{"a": [1, 2], "b": [11111, 22222]}"#,
+        "{\n  \"a\": [1,2],\n  \"b\": [\n    11111,\n    22222\n  ]\n}\n",
+      ),
+      (
+        &["--mask-keys", "password,secret", "--compact"],
+        r#"{"user":"a","password":"b","x":{"secret":[1]}}"#,
+        "{\"user\":\"a\",\"password\":\"***\",\"x\":{\"secret\":\"***\"}}\n",
+      ),
+      (
+        &["--stats-footer"],
+        r#"{"a":[1,2]}"#,
+        "{\n  \"a\": [\n    1,\n    2\n  ]\n}\n// stats: {objects:1, arrays:1, values:2, depth:2}\n",
+      ),
+      (
+        &["--rename-keys", "snake_case", "--compact"],
+        r#"{"userName":{"zipCode":1},"ID":[{"aB":2}]}"#,
+        "{\"user_name\":{\"zip_code\":1},\"id\":[{\"a_b\":2}]}\n",
+      ),
+    ];
+    for (args, input, expected) in tests {
+      let output = run(args, input)?;
+      assert_eq!(
+        "",
+        String::from_utf8_lossy(&output.stderr),
+        "args: {:?}",
+        args
+      );
+      assert!(output.status.success(), "args: {:?}", args);
+      assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        expected,
+        "args: {:?}",
+        args
+      );
+    }
+    Ok(())
+  }
+
+  #[test]
+  fn can_convert_stdin() -> Result<(), Box<dyn Error>> {
+    let tests: Vec<(&[&str], &str, &str)> = vec![
+      (
+        &["--template", "{{name}} is {{age}}"],
+        r#"{"name":"bob","age":42}"#,
+        "bob is 42\n",
+      ),
+      (
+        &["--to-dotenv", "APP_"],
+        r#"{"db":{"host":"x","port":1}}"#,
+        "APP_DB_HOST=\"x\"\nAPP_DB_PORT=1\n",
+      ),
+      (
+        &[
+          "--to-csv",
+          "--csv-delimiter",
+          "\\t",
+          "--csv-null-value",
+          "-",
+        ],
+        r#"[{"a":1,"b":"x"},{"a":null}]"#,
+        "a\tb\n1\tx\n-\t\n",
+      ),
+    ];
+    for (args, input, expected) in tests {
+      let output = run(args, input)?;
+      assert_eq!(
+        "",
+        String::from_utf8_lossy(&output.stderr),
+        "args: {:?}",
+        args
+      );
+      assert!(output.status.success(), "args: {:?}", args);
+      assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        expected,
+        "args: {:?}",
+        args
+      );
+    }
+    Ok(())
+  }
+
+  #[test]
+  fn can_set_indent() -> Result<(), Box<dyn Error>> {
+    let tests = [
+      (
+        vec!["--indent", "4", "--sort-by-name"],
+        "{\n    \"a\": 2,\n    \"b\": 1\n}\n",
+      ),
+      (vec!["--tab"], "{\n\t\"b\": 1,\n\t\"a\": 2\n}\n"),
+      (vec!["--indent", "0"], "{\n\"b\": 1,\n\"a\": 2\n}\n"),
+    ];
+    for (args, expected) in tests {
+      let output = run(&args, r#"{"b":1,"a":2}"#)?;
+      assert_eq!("", String::from_utf8_lossy(&output.stderr).to_string());
+      assert!(output.status.success());
+      assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        expected,
+        "args: {:?}",
+        args
+      );
+    }
+    Ok(())
+  }
+
+  #[test]
+  fn can_sort_by_editorconfig_order() -> Result<(), Box<dyn Error>> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("a.json").to_str().unwrap().to_owned();
+    fs::write(
+      dir.path().join(".editorconfig"),
+      "[*.json]\njson_key_order = name\n",
+    )?;
+
+    fs::write(&path, r#"{"b":1,"name":2}"#)?;
+    let output = run(&["--sort-by-name", &path], "")?;
+    assert_eq!("", String::from_utf8_lossy(&output.stderr).to_string());
+    assert!(output.status.success());
+    assert_eq!(
+      fs::read_to_string(&path)?,
+      "{\n  \"name\": 2,\n  \"b\": 1\n}\n"
+    );
+
+    fs::write(&path, r#"{"a":1,"name":2,"B":3,"c":4}"#)?;
+    let output = run(
+      &[
+        "--sort-by-name",
+        "--reverse",
+        "--case-insensitive",
+        "--explain-sort",
+        &path,
+      ],
+      "",
+    )?;
+    assert!(output.status.success());
+    assert_eq!(
+      String::from_utf8_lossy(&output.stderr),
+      "moved \"/name\" from position 1 to position 0\n\
+       moved \"/c\" from position 3 to position 1\n\
+       moved \"/a\" from position 0 to position 3\n"
+    );
+    assert_eq!(
+      fs::read_to_string(&path)?,
+      "{\n  \"name\": 2,\n  \"c\": 4,\n  \"B\": 3,\n  \"a\": 1\n}\n"
+    );
+
+    fs::write(&path, r#"{"name":2,"b":1}"#)?;
+    let output = run(&["--sort-by-name", "--no-editorconfig", &path], "")?;
+    assert_eq!("", String::from_utf8_lossy(&output.stderr).to_string());
+    assert!(output.status.success());
+    assert_eq!(
+      fs::read_to_string(&path)?,
+      "{\n  \"b\": 1,\n  \"name\": 2\n}\n"
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn can_process_multiple_files() -> Result<(), Box<dyn Error>> {
+    let files = [r#"{"b":1,"a":2}"#, "{", r#"[{"y":1,"x":2}]"#]
+      .iter()
+      .map(|content| {
+        let mut file = NamedTempFile::new()?;
+        file.write_all(content.as_bytes())?;
+        file.flush()?;
+        Ok(file)
+      })
+      .collect::<io::Result<Vec<_>>>()?;
+    let paths = files
+      .iter()
+      .map(|x| x.path().to_str().unwrap())
+      .collect::<Vec<_>>();
+
+    let output = Command::new("cargo")
+      .args(["run", "--quiet", "--", "--sort-by-name", "--compact"])
+      .args(&paths)
+      .output()?;
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with(&format!("{}: ", paths[1])));
+    assert_eq!(fs::read_to_string(paths[0])?, "{\"a\":2,\"b\":1}\n");
+    assert_eq!(fs::read_to_string(paths[1])?, "{");
+    assert_eq!(fs::read_to_string(paths[2])?, "[{\"x\":2,\"y\":1}]\n");
+    Ok(())
+  }
+
+  #[test]
+  fn writes_file_by_replacing_it() -> Result<(), Box<dyn Error>> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("a.json");
+    let link = dir.path().join("b.json");
+    fs::write(&path, r#"{"b":1,"a":2}"#)?;
+    fs::hard_link(&path, &link)?;
+
+    let output = Command::new("cargo")
+      .args(["run", "--quiet", "--", "--sort-by-name", "--compact"])
+      .arg(&path)
+      .output()?;
+
+    assert_eq!("", String::from_utf8_lossy(&output.stderr).to_string());
+    assert!(output.status.success());
+    assert_eq!(fs::read_to_string(&path)?, "{\"a\":2,\"b\":1}\n");
+    // The old file was renamed over rather than truncated and rewritten,
+    // so its other link still has the old content.
+    assert_eq!(fs::read_to_string(&link)?, r#"{"b":1,"a":2}"#);
+    assert_eq!(fs::read_dir(dir.path())?.count(), 2);
+    Ok(())
+  }
+
+  #[test]
+  fn can_sort_by_key_transform() -> Result<(), Box<dyn Error>> {
+    let output = run(
+      &["--key-transform", "tr abc cba", "--compact"],
+      r#"{"a":1,"b":{"c":2,"a":3},"c":4}"#,
+    )?;
+    assert_eq!("", String::from_utf8_lossy(&output.stderr).to_string());
+    assert!(output.status.success());
+    assert_eq!(
+      String::from_utf8_lossy(&output.stdout),
+      "{\"c\":4,\"b\":{\"c\":2,\"a\":3},\"a\":1}\n"
+    );
+
+    let output = run(&["--key-transform", "exit 3"], r#"{"a":1,"b":2}"#)?;
+    assert!(!output.status.success());
+    Ok(())
+  }
+
+  #[test]
+  fn can_sort_with_external_cmp() -> Result<(), Box<dyn Error>> {
+    let by_length = "read a; read b; \
+      if [ ${#a} -lt ${#b} ]; then echo '<'; elif [ ${#a} -gt ${#b} ]; then echo '>'; else echo =; fi";
+    let output = run(
+      &["--sort-with-external-cmp", by_length, "--compact"],
+      r#"{"ccc":1,"a":2,"bb":{"yy":3,"x":4}}"#,
+    )?;
+    assert_eq!("", String::from_utf8_lossy(&output.stderr).to_string());
+    assert!(output.status.success());
+    assert_eq!(
+      String::from_utf8_lossy(&output.stdout),
+      "{\"a\":2,\"bb\":{\"x\":4,\"yy\":3},\"ccc\":1}\n"
+    );
+
+    let output = run(
+      &[
+        "--sort-with-external-cmp",
+        "exec sleep 5",
+        "--cmp-timeout",
+        "50",
+        "--compact",
+      ],
+      r#"{"b":1,"aa":2}"#,
+    )?;
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("timed out"));
+    assert_eq!(
+      String::from_utf8_lossy(&output.stdout),
+      "{\"aa\":2,\"b\":1}\n"
+    );
+
+    let output = run(&["--sort-with-external-cmp", "echo x"], r#"{"a":1,"b":2}"#)?;
+    assert!(!output.status.success());
+    Ok(())
+  }
+
+  #[test]
+  fn can_backup_files() -> Result<(), Box<dyn Error>> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("a.json");
+    for (flag, suffix) in [("--backup", ".bak"), ("--backup=.orig", ".orig")] {
+      fs::write(&path, r#"{"b":1,"a":2}"#)?;
+      fs::write(dir.path().join(format!("a.json{}", suffix)), "old")?;
+
+      let output = Command::new("cargo")
+        .args(["run", "--quiet", "--", "--sort-by-name", "--compact", flag])
+        .arg(&path)
+        .output()?;
+
+      assert_eq!("", String::from_utf8_lossy(&output.stderr).to_string());
+      assert!(output.status.success());
+      assert_eq!(fs::read_to_string(&path)?, "{\"a\":2,\"b\":1}\n");
+      assert_eq!(
+        fs::read_to_string(dir.path().join(format!("a.json{}", suffix)))?,
+        r#"{"b":1,"a":2}"#
+      );
+    }
+    Ok(())
+  }
+
+  #[test]
+  fn can_diff() -> Result<(), Box<dyn Error>> {
+    let mut temp = NamedTempFile::new()?;
+    temp.write_all(b"{\n  \"b\": 1,\n  \"a\": 2\n}\n")?;
+    temp.flush()?;
+    let path = temp.path().to_str().unwrap();
+
+    let output = Command::new("cargo")
+      .args(["run", "--quiet", "--", "--sort-by-name", "--diff", path])
+      .output()?;
+    assert_eq!("", String::from_utf8_lossy(&output.stderr).to_string());
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+      String::from_utf8_lossy(&output.stdout),
+      format!(
+        "--- a/{0}\n+++ b/{0}\n@@ -1,4 +1,4 @@\n {{\n-  \"b\": 1,\n-  \"a\": 2\n+  \"a\": 2,\n+  \"b\": 1\n }}\n",
+        path
+      )
+    );
+    assert_eq!(fs::read_to_string(path)?, "{\n  \"b\": 1,\n  \"a\": 2\n}\n");
+
+    let output = run(&["--sort-by-name", "--diff"], "{\n  \"a\": 1\n}\n")?;
+    assert!(output.status.success());
+    assert_eq!("", String::from_utf8_lossy(&output.stdout));
+    Ok(())
+  }
+
+  #[test]
+  fn can_report_parse_error_location() -> Result<(), Box<dyn Error>> {
+    let output = run(&[], "{\"a\": 1\n  \"b\": 2}")?;
+    assert!(!output.status.success());
+    assert_eq!(
+      String::from_utf8_lossy(&output.stderr),
+      "2:3: unexpected '\"'\n"
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn can_parse_leniently() -> Result<(), Box<dyn Error>> {
+    let input = "{\"a\": [1, 2,], \"b\": {},}";
+    assert!(!run(&["--compact"], input)?.status.success());
+
+    let output = run(&["--lenient", "--compact"], input)?;
+    assert!(output.status.success());
+    assert_eq!(
+      String::from_utf8_lossy(&output.stdout),
+      "{\"a\":[1,2],\"b\":{}}\n"
+    );
+
+    let output = run(
+      &["--lenient", "--sort-by-name", "--compact"],
+      "{b: 1, a: 2}",
+    )?;
+    assert!(output.status.success());
+    assert_eq!(
+      String::from_utf8_lossy(&output.stdout),
+      "{\"a\":2,\"b\":1}\n"
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn can_strip_comments() -> Result<(), Box<dyn Error>> {
+    let input = "{\n  // b\n  \"b\": 1, /* a */ \"a\": \"/* kept */\"\n}\n";
+    assert!(!run(&["--compact"], input)?.status.success());
+
+    let output = run(&["--jsonc", "--sort-by-name", "--compact"], input)?;
+    assert!(output.status.success());
+    assert_eq!(
+      String::from_utf8_lossy(&output.stdout),
+      "{\"a\":\"/* kept */\",\"b\":1}\n"
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn can_process_jsonl() -> Result<(), Box<dyn Error>> {
+    let output = run(
+      &["--jsonl", "--sort-by-name"],
+      "{\"b\": 1, \"a\": 2}\n\n{bad\n[ 1, {\"d\":0,\"c\":0} ]\n",
+    )?;
+    assert!(output.status.success());
+    assert_eq!(
+      String::from_utf8_lossy(&output.stdout),
+      "{\"a\":2,\"b\":1}\n{bad\n[1,{\"c\":0,\"d\":0}]\n"
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("warning: line 3: "));
+    Ok(())
+  }
+
+  #[test]
+  fn can_explain_sort() -> Result<(), Box<dyn Error>> {
+    let output = run(
+      &["--sort-by-name", "--explain-sort", "--compact"],
+      r#"{"b":1,"a":2}"#,
+    )?;
+    assert!(output.status.success());
+    assert_eq!(
+      String::from_utf8_lossy(&output.stdout),
+      "{\"a\":2,\"b\":1}\n"
+    );
+    assert_eq!(
+      String::from_utf8_lossy(&output.stderr),
+      "moved \"/a\" from position 1 to position 0\nmoved \"/b\" from position 0 to position 1\n"
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn can_sort_by_value() -> Result<(), Box<dyn Error>> {
+    let mut temp = NamedTempFile::new()?;
+    let path = temp.path().to_str().unwrap().to_owned();
+    temp.write_all(r#"[{"x":1},{"x":0}]"#.as_bytes())?;
+    temp.flush()?;
+
+    let output = Command::new("cargo")
+      .args(["run", "--quiet", "--", "--sort-by-value", "x", &path])
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .spawn()?
+      .wait_with_output()?;
+
+    assert_eq!("", String::from_utf8_lossy(&output.stdout).to_string());
+    assert_eq!("", String::from_utf8_lossy(&output.stderr).to_string());
+    assert!(output.status.success());
+    assert_eq!(
+      fs::read_to_string(&path)?,
+      r#"[
+  {
+    "x": 0
+  },
+  {
+    "x": 1
+  }
+]
+"#
+      .to_owned()
+    );
+    Ok(())
+  }
 
-    Ok(mut node) => {
-      if args.sort_by_name {
-        node.sort_by_name();
-      }
+  #[test]
+  fn can_sort_secondary_by_value() -> Result<(), Box<dyn Error>> {
+    let output = run(
+      &["--sort-secondary-by-value", "id"],
+      r#"[{"id":2,"a":1},{"a":1,"id":1}]"#,
+    )?;
+    assert_eq!("", String::from_utf8_lossy(&output.stderr).to_string());
+    assert!(output.status.success());
+    assert_eq!(
+      String::from_utf8_lossy(&output.stdout),
+      r#"[
+  {
+    "a": 1,
+    "id": 1
+  },
+  {
+    "a": 1,
+    "id": 2
+  }
+]
+"#
+    );
+    Ok(())
+  }
 
-      if let Some(name) = args.sort_by_value.as_ref() {
-        node.sort_by_value(name);
-      }
+  #[test]
+  fn can_estimate_output_size() -> Result<(), Box<dyn Error>> {
+    let output = run(&["--dry-run-size"], r#"{"a":[1,2]}"#)?;
+    assert!(output.status.success());
+    assert_eq!("", String::from_utf8_lossy(&output.stdout));
+    assert_eq!("29\n", String::from_utf8_lossy(&output.stderr));
+    Ok(())
+  }
 
-      let mut output = node.to_string();
-      output.push('\n');
-      write_output(&args, &output)?;
+  #[test]
+  fn can_alias_keys() -> Result<(), Box<dyn Error>> {
+    let mut aliases = NamedTempFile::new()?;
+    aliases.write_all(r#"{"usr_nm":"user_name","id":"\"id\""}"#.as_bytes())?;
+    aliases.flush()?;
+    let path = aliases.path().to_str().unwrap();
 
-      Ok(())
-    }
+    let output = run(
+      &["--alias-keys-file", path, "--compact"],
+      r#"{"usr_nm":"a","id":1,"x":{"usr_nm":2}}"#,
+    )?;
+    assert_eq!("", String::from_utf8_lossy(&output.stderr).to_string());
+    assert!(output.status.success());
+    assert_eq!(
+      String::from_utf8_lossy(&output.stdout),
+      "{\"user_name\":\"a\",\"\\\"id\\\"\":1,\"x\":{\"user_name\":2}}\n"
+    );
+    Ok(())
   }
-}
 
-fn read_input(args: &Args) -> io::Result<String> {
-  if let Some(path) = args.file.as_ref() {
-    fs::read_to_string(path)
-  } else {
-    let mut input = String::new();
-    io::stdin().read_to_string(&mut input)?;
-    Ok(input)
+  #[test]
+  fn can_sort_typed_values() -> Result<(), Box<dyn Error>> {
+    let output = run(
+      &["--sort-typed-values", "k"],
+      r#"[{"k":"a"},{"k":1},{"k":null}]"#,
+    )?;
+    assert_eq!("", String::from_utf8_lossy(&output.stderr).to_string());
+    assert!(output.status.success());
+    assert_eq!(
+      String::from_utf8_lossy(&output.stdout),
+      r#"[
+  {
+    "k": null
+  },
+  {
+    "k": 1
+  },
+  {
+    "k": "a"
   }
-}
-
-fn write_output(args: &Args, output: &str) -> io::Result<()> {
-  if let Some(path) = args.file.as_ref() {
-    fs::write(path, output)
-  } else {
-    print!("{}", output);
+]
+"#
+    );
     Ok(())
   }
-}
-
-#[cfg(test)]
-mod tests {
-  use std::{
-    error::Error,
-    fs,
-    io::{self, Write},
-    process::{Command, Stdio},
-  };
-  use tempfile::NamedTempFile;
 
   #[test]
-  fn can_use_stdin_stdout() -> io::Result<()> {
-    let mut proc = Command::new("cargo")
-      .args(["run"])
-      .stdin(Stdio::piped())
-      .stdout(Stdio::piped())
-      .spawn()?;
-    proc.stdin.as_mut().unwrap().write(b"{ }")?;
-    let output = proc.wait_with_output()?;
+  fn can_verify_sort() -> Result<(), Box<dyn Error>> {
+    let output = run(&["--verify-sort"], r#"{"b":1,"a":2}"#)?;
+    assert_eq!("", String::from_utf8_lossy(&output.stderr).to_string());
     assert!(output.status.success());
-    assert_eq!(output.stdout, b"{}\n");
+
+    let output = run(&["--verify-sort", "--verify-runs", "3"], r#"{"a":1,"a":2}"#)?;
+    assert!(!output.status.success());
+    assert_eq!(
+      String::from_utf8_lossy(&output.stderr),
+      "sort run 1 differs at line 2:\n-   \"a\": 1,\n+   \"a\": 2,\n"
+    );
     Ok(())
   }
 
   #[test]
-  fn can_use_file() -> Result<(), Box<dyn Error>> {
+  fn can_skip_sorted_file_with_idempotent() -> Result<(), Box<dyn Error>> {
     let mut temp = NamedTempFile::new()?;
     let path = temp.path().to_str().unwrap().to_owned();
-    temp.write(b"{ }")?;
+    temp.write_all(r#"{"0":0,"1":0}"#.as_bytes())?;
     temp.flush()?;
 
-    let output = Command::new("cargo")
-      .args(["run", "--quiet", "--", &path])
-      .stdout(Stdio::piped())
-      .stderr(Stdio::piped())
-      .spawn()?
-      .wait_with_output()?;
-
-    assert_eq!("", String::from_utf8_lossy(&output.stdout).to_string());
+    let output = run(&["--sort-by-name", "--idempotent", &path], "")?;
     assert_eq!("", String::from_utf8_lossy(&output.stderr).to_string());
     assert!(output.status.success());
-    assert_eq!(&fs::read_to_string(&path)?, "{}\n");
+    assert_eq!(fs::read_to_string(&path)?, r#"{"0":0,"1":0}"#);
+
+    let output = run(
+      &[
+        "--sort-by-name",
+        "--idempotent",
+        "--trim-strings",
+        "--compact",
+        &path,
+      ],
+      "",
+    )?;
+    assert!(output.status.success());
+    assert_eq!(fs::read_to_string(&path)?, "{\"0\":0,\"1\":0}\n");
+
+    fs::write(&path, r#"{"a":" x "}"#)?;
+    let output = run(
+      &["--sort-by-name", "--idempotent", "--trim-strings", &path],
+      "",
+    )?;
+    assert!(output.status.success());
+    assert_eq!(fs::read_to_string(&path)?, "{\n  \"a\": \"x\"\n}\n");
     Ok(())
   }
 
   #[test]
-  fn can_sort_by_name() -> Result<(), Box<dyn Error>> {
+  fn can_check() -> Result<(), Box<dyn Error>> {
+    let output = run(&["--check", "--sort-by-name"], "{\n  \"a\": 1\n}\n")?;
+    assert_eq!("", String::from_utf8_lossy(&output.stderr).to_string());
+    assert!(output.status.success());
+    assert_eq!("", String::from_utf8_lossy(&output.stdout).to_string());
+
+    let output = run(&["--check", "--sort-by-name"], "{\n  \"a\": 1\n}")?;
+    assert!(!output.status.success());
+    assert_eq!(
+      String::from_utf8_lossy(&output.stderr),
+      "<stdin>: would be reformatted\n"
+    );
+
     let mut temp = NamedTempFile::new()?;
     let path = temp.path().to_str().unwrap().to_owned();
-    temp.write(r#"{"1":0,"0":0}"#.as_bytes())?;
+    temp.write_all(r#"{"b":1,"a":2}"#.as_bytes())?;
     temp.flush()?;
 
-    let output = Command::new("cargo")
-      .args(["run", "--quiet", "--", "--sort-by-name", &path])
-      .stdout(Stdio::piped())
-      .stderr(Stdio::piped())
-      .spawn()?
-      .wait_with_output()?;
+    let output = run(&["--check", "--sort-by-name", &path], "")?;
+    assert!(!output.status.success());
+    assert_eq!(
+      String::from_utf8_lossy(&output.stderr),
+      format!("{}: would be reformatted\n", path)
+    );
+    assert_eq!(fs::read_to_string(&path)?, r#"{"b":1,"a":2}"#);
+
+    let sorted = NamedTempFile::new()?;
+    let sorted_path = sorted.path().to_str().unwrap().to_owned();
+    fs::write(&sorted_path, "{\n  \"a\": 1\n}\n")?;
+    let other = NamedTempFile::new()?;
+    let other_path = other.path().to_str().unwrap().to_owned();
+    fs::write(&other_path, "[]")?;
+    let output = run(
+      &[
+        "--check",
+        "--sort-by-name",
+        &path,
+        &sorted_path,
+        &other_path,
+      ],
+      "",
+    )?;
+    assert!(!output.status.success());
+    assert_eq!(
+      String::from_utf8_lossy(&output.stderr),
+      format!(
+        "{}: would be reformatted\n{}: would be reformatted\n",
+        path, other_path
+      )
+    );
+    Ok(())
+  }
 
+  #[test]
+  fn can_validate_convention() -> Result<(), Box<dyn Error>> {
+    let output = run(&["--validate-convention", "snake"], r#"{"a_b":{"cD":1}}"#)?;
     assert_eq!("", String::from_utf8_lossy(&output.stdout).to_string());
+    assert_eq!(
+      String::from_utf8_lossy(&output.stderr),
+      "/a_b/cD: key `cD` is not snake_case\n"
+    );
+    assert!(!output.status.success());
+
+    let a = NamedTempFile::new()?;
+    let a_path = a.path().to_str().unwrap().to_owned();
+    fs::write(&a_path, r#"{"aB":1,"cD":2}"#)?;
+    let b = NamedTempFile::new()?;
+    let b_path = b.path().to_str().unwrap().to_owned();
+    fs::write(&b_path, r#"{"eF":1}"#)?;
+    let output = run(&["--validate-convention", "snake", &a_path, &b_path], "")?;
+    assert!(!output.status.success());
+    assert_eq!(
+      String::from_utf8_lossy(&output.stderr),
+      format!(
+        "{}: /aB: key `aB` is not snake_case\n/cD: key `cD` is not snake_case\n\
+         {}: /eF: key `eF` is not snake_case\n",
+        a_path, b_path
+      )
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn can_warn_mixed_escapes() -> Result<(), Box<dyn Error>> {
+    let output = run(&["--warn-mixed-escapes"], r#"{"a":"\"\u0022"}"#)?;
+    assert!(output.status.success());
+    assert_eq!(
+      String::from_utf8_lossy(&output.stderr),
+      "warning: /a: string mixes escape styles\n"
+    );
+    assert_eq!(
+      String::from_utf8_lossy(&output.stdout),
+      "{\n  \"a\": \"\\\"\\u0022\"\n}\n"
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn can_read_from_env() -> Result<(), Box<dyn Error>> {
+    let output = Command::new("cargo")
+      .args(["run", "--quiet", "--", "--from-env", "JSONSRT_APP"])
+      .env("JSONSRT_APP_DB_HOST", "localhost")
+      .env("JSONSRT_APP_DB_PORT", "5432")
+      .output()?;
     assert_eq!("", String::from_utf8_lossy(&output.stderr).to_string());
     assert!(output.status.success());
     assert_eq!(
-      fs::read_to_string(&path)?,
+      String::from_utf8_lossy(&output.stdout),
       r#"{
-  "0": 0,
-  "1": 0
+  "db_host": "localhost",
+  "db_port": "5432"
 }
 "#
-      .to_owned()
     );
     Ok(())
   }
 
+  #[cfg(unix)]
   #[test]
-  fn can_sort_by_value() -> Result<(), Box<dyn Error>> {
-    let mut temp = NamedTempFile::new()?;
-    let path = temp.path().to_str().unwrap().to_owned();
-    temp.write(r#"[{"x":1},{"x":0}]"#.as_bytes())?;
-    temp.flush()?;
+  fn can_read_from_env_with_non_utf8_vars() -> Result<(), Box<dyn Error>> {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
 
     let output = Command::new("cargo")
-      .args(["run", "--quiet", "--", "--sort-by-value", "x", &path])
-      .stdout(Stdio::piped())
-      .stderr(Stdio::piped())
-      .spawn()?
-      .wait_with_output()?;
+      .args([
+        "run",
+        "--quiet",
+        "--",
+        "--from-env",
+        "JSONSRT_APP",
+        "--compact",
+      ])
+      .env("JSONSRT_APP_A", "1")
+      .env("JSONSRT_APP_B", OsStr::from_bytes(b"\xff"))
+      .env("JSONSRT_OTHER", OsStr::from_bytes(b"\xff"))
+      .output()?;
+    assert_eq!("", String::from_utf8_lossy(&output.stderr).to_string());
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "{\"a\":\"1\"}\n");
+    Ok(())
+  }
 
-    assert_eq!("", String::from_utf8_lossy(&output.stdout).to_string());
+  #[test]
+  fn can_extract_values_by_type() -> Result<(), Box<dyn Error>> {
+    let output = run(
+      &["--extract-type", "number"],
+      r#"{"a":"x","b":[1,{"c":2}]}"#,
+    )?;
     assert_eq!("", String::from_utf8_lossy(&output.stderr).to_string());
     assert!(output.status.success());
     assert_eq!(
-      fs::read_to_string(&path)?,
+      String::from_utf8_lossy(&output.stdout),
       r#"[
   {
-    "x": 0
+    "path": "/b/0",
+    "value": 1
   },
   {
-    "x": 1
+    "path": "/b/1/c",
+    "value": 2
   }
 ]
 "#
-      .to_owned()
     );
     Ok(())
   }
+
+  #[test]
+  fn can_debug_sort_order() -> Result<(), Box<dyn Error>> {
+    let output = run(
+      &["--sort-by-name", "--debug-sort-order"],
+      r#"{"b":1,"a":2}"#,
+    )?;
+    assert!(output.status.success());
+    assert_eq!(
+      String::from_utf8_lossy(&output.stdout),
+      "{\n  \"a\": 2,\n  \"b\": 1\n}\n"
+    );
+    assert_eq!(
+      String::from_utf8_lossy(&output.stderr),
+      "[\n  [\n    \"a\",\n    \"b\"\n  ]\n]\n"
+    );
+
+    let mut temp = NamedTempFile::new()?;
+    let path = temp.path().to_str().unwrap().to_owned();
+    temp.write_all(r#"[{"b":1,"a":2}]"#.as_bytes())?;
+    temp.flush()?;
+
+    let output = run(&["--sort-by-name", "--debug-sort-order", &path], "")?;
+    assert_eq!("", String::from_utf8_lossy(&output.stderr).to_string());
+    assert!(output.status.success());
+    let sidecar = format!("{}.sort_order.json", path);
+    let order = fs::read_to_string(&sidecar);
+    fs::remove_file(&sidecar)?;
+    assert_eq!(order?, "[\n  [\n    \"a\",\n    \"b\"\n  ]\n]\n");
+    Ok(())
+  }
+
+  #[test]
+  fn can_concat_arrays() -> Result<(), Box<dyn Error>> {
+    let mut other = NamedTempFile::new()?;
+    let path = other.path().to_str().unwrap().to_owned();
+    other.write_all(b"[3]")?;
+    other.flush()?;
+
+    let output = run(&["--concat", &path], "[1,2]")?;
+    assert_eq!("", String::from_utf8_lossy(&output.stderr).to_string());
+    assert!(output.status.success());
+    assert_eq!(
+      String::from_utf8_lossy(&output.stdout),
+      "[\n  1,\n  2,\n  3\n]\n"
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn can_display_unescaped_strings() -> Result<(), Box<dyn Error>> {
+    let output = run(&["--display-unescape"], r#"{"a":"x\ny"}"#)?;
+    assert!(output.status.success());
+    assert_eq!(
+      String::from_utf8_lossy(&output.stdout),
+      "{\n  \"a\": \"x\ny\"\n}\n"
+    );
+    assert_eq!(
+      String::from_utf8_lossy(&output.stderr),
+      "warning: --display-unescape output is not valid JSON\n"
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn can_filter_keys() -> Result<(), Box<dyn Error>> {
+    let input = r#"{"a":1,"b":[{"a":2,"c":3}],"c":{"b":4}}"#;
+    let tests = [
+      ("--exclude-keys", "a,c", r#"{"b":[{}]}"#),
+      ("--only-keys", "b,c", r#"{"b":[{"c":3}],"c":{"b":4}}"#),
+    ];
+    for (flag, keys, expected) in tests {
+      let output = run(&[flag, keys, "--compact"], input)?;
+      assert!(output.status.success());
+      assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!("{}\n", expected)
+      );
+    }
+    Ok(())
+  }
+
+  #[test]
+  fn can_merge_objects() -> Result<(), Box<dyn Error>> {
+    let mut other = NamedTempFile::new()?;
+    let path = other.path().to_str().unwrap().to_owned();
+    other.write_all(br#"{"b":{"d":3},"a":0}"#)?;
+    other.flush()?;
+
+    let output = run(
+      &["--merge", &path, "--sort-by-name", "--compact"],
+      r#"{"b":{"c":1},"a":2}"#,
+    )?;
+    assert_eq!("", String::from_utf8_lossy(&output.stderr).to_string());
+    assert!(output.status.success());
+    assert_eq!(
+      String::from_utf8_lossy(&output.stdout),
+      "{\"a\":0,\"b\":{\"c\":1,\"d\":3}}\n"
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn can_emit_schema() -> Result<(), Box<dyn Error>> {
+    let schema = NamedTempFile::new()?;
+    let path = schema.path().to_str().unwrap().to_owned();
+
+    let output = run(&["--emit-schema", &path, "a.json", "b.json"], "")?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+      .contains("--emit-schema cannot be used with more than one file"));
+
+    let output = run(
+      &["--emit-schema", &path],
+      r#"{"type":"object","properties":{"b":{},"a":{}}}"#,
+    )?;
+    assert_eq!("", String::from_utf8_lossy(&output.stderr).to_string());
+    assert!(output.status.success());
+    assert_eq!(
+      String::from_utf8_lossy(&output.stdout),
+      r#"{
+  "type": "object",
+  "properties": {
+    "b": {},
+    "a": {}
+  }
+}
+"#
+    );
+    assert_eq!(
+      fs::read_to_string(&path)?,
+      r#"{
+  "properties": {
+    "a": {},
+    "b": {}
+  },
+  "type": "object"
+}
+"#
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn can_format_with_schema() -> Result<(), Box<dyn Error>> {
+    let mut schema = NamedTempFile::new()?;
+    let path = schema.path().to_str().unwrap().to_owned();
+    schema.write_all(r#"{"properties":{"b":{},"a":{}}}"#.as_bytes())?;
+    schema.flush()?;
+
+    let output = run(&["--format-with-schema", &path], r#"{"c":1,"a":2,"b":3}"#)?;
+    assert_eq!("", String::from_utf8_lossy(&output.stderr).to_string());
+    assert!(output.status.success());
+    assert_eq!(
+      String::from_utf8_lossy(&output.stdout),
+      r#"{
+  "b": 3,
+  "a": 2,
+  "c": 1
+}
+"#
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn can_add_schema_docs() -> Result<(), Box<dyn Error>> {
+    let mut schema = NamedTempFile::new()?;
+    let path = schema.path().to_str().unwrap().to_owned();
+    schema.write_all(r#"{"properties":{"name":{"description":"Full name"}}}"#.as_bytes())?;
+    schema.flush()?;
+
+    let output = run(&["--schema-docs", &path], r#"{"name":"a","id":1}"#)?;
+    assert_eq!("", String::from_utf8_lossy(&output.stderr).to_string());
+    assert!(output.status.success());
+    assert_eq!(
+      String::from_utf8_lossy(&output.stdout),
+      r#"{
+  // Full name
+  "name": "a",
+  "id": 1
+}
+"#
+    );
+    Ok(())
+  }
+
+  fn run(args: &[&str], input: &str) -> io::Result<Output> {
+    let mut proc = Command::new("cargo")
+      .args(["run", "--quiet", "--"])
+      .args(args)
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .spawn()?;
+    proc.stdin.as_mut().unwrap().write_all(input.as_bytes())?;
+    proc.wait_with_output()
+  }
 }
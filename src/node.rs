@@ -1,6 +1,374 @@
-#[derive(Debug, PartialEq)]
+use crate::sort::unquote;
+use clap::ValueEnum;
+use std::{borrow::Cow, fmt};
+use Node::{Array, Object, Value};
+
+/// A JSON document. Keys and values hold their raw source text, including
+/// the quotes around strings. Parsed nodes borrow from the input, while
+/// transformed nodes may own newly built text.
+#[derive(Debug, Clone, PartialEq)]
 pub enum Node<'a> {
-  Object(Vec<(&'a str, Node<'a>)>),
+  Object(Vec<(Cow<'a, str>, Node<'a>)>),
   Array(Vec<Node<'a>>),
-  Value(&'a str),
+  Value(Cow<'a, str>),
+}
+
+/// A node that owns all of its text, so it can outlive the parsed input.
+pub type OwnedNode = Node<'static>;
+
+/// Escapes an unquoted object key for use as a JSON Pointer reference token.
+pub fn pointer_token(key: &str) -> String {
+  key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Counts of the nodes in a tree, from `Node::count`. `depth` is the
+/// deepest nesting of arrays and objects, 0 for a lone value.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NodeStats {
+  pub objects: usize,
+  pub arrays: usize,
+  pub values: usize,
+  pub depth: usize,
+}
+
+impl fmt::Display for NodeStats {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{{objects:{}, arrays:{}, values:{}, depth:{}}}",
+      self.objects, self.arrays, self.values, self.depth
+    )
+  }
+}
+
+/// The type of a JSON leaf value.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum JsonType {
+  String,
+  Number,
+  Bool,
+  Null,
+}
+
+impl JsonType {
+  /// Returns the type of a raw leaf value.
+  pub fn of(value: &str) -> JsonType {
+    match value {
+      "true" | "false" => JsonType::Bool,
+      "null" => JsonType::Null,
+      _ if value.starts_with('"') => JsonType::String,
+      _ => JsonType::Number,
+    }
+  }
+}
+
+impl<'a> Node<'a> {
+  /// Returns the value of the first entry of an object with the unquoted
+  /// key `key`.
+  pub fn get(&self, key: &str) -> Option<&Node<'a>> {
+    match self {
+      Object(xs) => xs.iter().find(|(k, _)| unquote(k) == key).map(|(_, x)| x),
+      _ => None,
+    }
+  }
+
+  /// Like `get`, but returns a mutable reference.
+  pub fn get_mut(&mut self, key: &str) -> Option<&mut Node<'a>> {
+    match self {
+      Object(xs) => xs
+        .iter_mut()
+        .find(|(k, _)| unquote(k) == key)
+        .map(|(_, x)| x),
+      _ => None,
+    }
+  }
+
+  /// Returns the element of an array, or the value of an object entry, at
+  /// position `i`.
+  pub fn get_index(&self, i: usize) -> Option<&Node<'a>> {
+    match self {
+      Value(_) => None,
+      Object(xs) => xs.get(i).map(|(_, x)| x),
+      Array(xs) => xs.get(i),
+    }
+  }
+
+  /// Like `get_index`, but returns a mutable reference.
+  pub fn get_index_mut(&mut self, i: usize) -> Option<&mut Node<'a>> {
+    match self {
+      Value(_) => None,
+      Object(xs) => xs.get_mut(i).map(|(_, x)| x),
+      Array(xs) => xs.get_mut(i),
+    }
+  }
+
+  /// Copies the text borrowed from the input, so the copy can be kept after
+  /// the input is dropped.
+  pub fn to_owned(&self) -> OwnedNode {
+    let owned = |x: &Cow<str>| Cow::Owned(x.to_string());
+    match self {
+      Value(x) => Value(owned(x)),
+      Object(xs) => Object(xs.iter().map(|(k, x)| (owned(k), x.to_owned())).collect()),
+      Array(xs) => Array(xs.iter().map(Node::to_owned).collect()),
+    }
+  }
+
+  /// Calls `visit` with the JSON Pointer and node of every node in the tree,
+  /// parents before children.
+  pub fn walk<'s>(&'s self, visit: &mut impl FnMut(&str, &'s Node<'a>)) {
+    self.walk_at("", visit);
+  }
+
+  fn walk_at<'s>(&'s self, path: &str, visit: &mut impl FnMut(&str, &'s Node<'a>)) {
+    visit(path, self);
+    match self {
+      Value(_) => {}
+      Object(xs) => xs.iter().for_each(|(key, x)| {
+        x.walk_at(&format!("{}/{}", path, pointer_token(unquote(key))), visit)
+      }),
+      Array(xs) => xs
+        .iter()
+        .enumerate()
+        .for_each(|(i, x)| x.walk_at(&format!("{}/{}", path, i), visit)),
+    }
+  }
+
+  /// Returns the JSON Pointer and raw value of every leaf of type `t`.
+  pub fn extract_values_by_type(&self, t: JsonType) -> Vec<(String, &str)> {
+    let mut values = vec![];
+    self.walk(&mut |path, node| {
+      if let Value(x) = node {
+        if JsonType::of(x) == t {
+          values.push((path.to_owned(), x.as_ref()));
+        }
+      }
+    });
+    values
+  }
+
+  /// Counts the objects, arrays and values in the tree.
+  pub fn count(&self) -> NodeStats {
+    let mut stats = NodeStats {
+      depth: self.depth(),
+      ..Default::default()
+    };
+    self.count_into(&mut stats);
+    stats
+  }
+
+  fn count_into(&self, stats: &mut NodeStats) {
+    match self {
+      Value(_) => stats.values += 1,
+      Object(xs) => {
+        stats.objects += 1;
+        xs.iter().for_each(|(_, x)| x.count_into(stats));
+      }
+      Array(xs) => {
+        stats.arrays += 1;
+        xs.iter().for_each(|x| x.count_into(stats));
+      }
+    }
+  }
+
+  /// Returns the deepest nesting of arrays and objects, 0 for a value and 1
+  /// for an empty array or object.
+  pub fn depth(&self) -> usize {
+    match self {
+      Value(_) => 0,
+      Object(xs) => 1 + xs.iter().map(|(_, x)| x.depth()).max().unwrap_or(0),
+      Array(xs) => 1 + xs.iter().map(Node::depth).max().unwrap_or(0),
+    }
+  }
+
+  /// Estimates the number of characters in the output of `to_string`,
+  /// without building it, by summing the keys, values, punctuation and two
+  /// spaces of indentation per level.
+  pub fn count_total_characters(&self) -> usize {
+    self.count_characters(0)
+  }
+
+  fn count_characters(&self, level: usize) -> usize {
+    // Each entry takes its indentation and a line break, after a comma for
+    // all but the last.
+    let entries = |n: usize| n * (2 * (level + 1) + 1) + n - 1;
+    // The brackets, the line break after the opening one and the indentation
+    // of the closing one.
+    let brackets = 3 + 2 * level;
+    match self {
+      Value(x) => x.chars().count(),
+      Array(xs) if xs.is_empty() => 2,
+      Object(xs) if xs.is_empty() => 2,
+      Array(xs) => {
+        brackets
+          + entries(xs.len())
+          + xs
+            .iter()
+            .map(|x| x.count_characters(level + 1))
+            .sum::<usize>()
+      }
+      Object(xs) => {
+        brackets
+          + entries(xs.len())
+          + xs
+            .iter()
+            .map(|(key, x)| key.chars().count() + 2 + x.count_characters(level + 1))
+            .sum::<usize>()
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parse::parse;
+
+  #[test]
+  fn get() {
+    let node = parse(r#"{"a":1,"b":{"c":[]},"a":2}"#).unwrap();
+    let tests = [
+      ("a", Some(Value("1".into()))),
+      ("b", Some(Object(vec![("\"c\"".into(), Array(vec![]))]))),
+      ("\"a\"", None),
+      ("x", None),
+    ];
+    for (key, expected) in tests {
+      assert_eq!(node.get(key), expected.as_ref(), "key: {}", key);
+    }
+    assert_eq!(node.get("b").and_then(|x| x.get("c")), Some(&Array(vec![])));
+    assert_eq!(Array(vec![]).get("a"), None);
+    assert_eq!(Value("\"a\"".into()).get("a"), None);
+  }
+
+  #[test]
+  fn get_index() {
+    let tests = [
+      ("[1,[2]]", 1, Some("[2]")),
+      ("[1,[2]]", 2, None),
+      (r#"{"b":1,"a":{"c":2}}"#, 1, Some(r#"{"c":2}"#)),
+      (r#"{"b":1}"#, 1, None),
+      ("1", 0, None),
+    ];
+    for (input, i, expected) in tests {
+      assert_eq!(
+        parse(input).unwrap().get_index(i),
+        expected.map(|x| parse(x).unwrap()).as_ref(),
+        "input: {}, index: {}",
+        input,
+        i
+      );
+    }
+  }
+
+  #[test]
+  fn get_mut() {
+    let mut node = parse(r#"{"a":[1,{"b":2}],"c":3}"#).unwrap();
+    *node.get_mut("c").unwrap() = Value("4".into());
+    let b = node
+      .get_mut("a")
+      .and_then(|x| x.get_index_mut(1))
+      .and_then(|x| x.get_mut("b"))
+      .unwrap();
+    *b = Array(vec![]);
+    *node
+      .get_index_mut(0)
+      .and_then(|x| x.get_index_mut(0))
+      .unwrap() = Value("0".into());
+    assert_eq!(node.to_compact_string(), r#"{"a":[0,{"b":[]}],"c":4}"#);
+    assert_eq!(node.get_mut("x"), None);
+    assert_eq!(Value("1".into()).get_index_mut(0), None);
+  }
+
+  #[test]
+  fn to_owned() {
+    let input = r#"{"a":[1,{"b":"c"}],"d":null}"#.to_owned();
+    let node: OwnedNode = parse(&input).unwrap().to_owned();
+    drop(input);
+    assert_eq!(node.to_compact_string(), r#"{"a":[1,{"b":"c"}],"d":null}"#);
+  }
+
+  #[test]
+  fn extract_values_by_type() {
+    let input = r#"{"a":"x","b":[1,true,null,"y"],"c/d":{"e~":2.5},"f":false}"#;
+    let tests = [
+      (JsonType::String, vec![("/a", r#""x""#), ("/b/3", r#""y""#)]),
+      (JsonType::Number, vec![("/b/0", "1"), ("/c~1d/e~0", "2.5")]),
+      (JsonType::Bool, vec![("/b/1", "true"), ("/f", "false")]),
+      (JsonType::Null, vec![("/b/2", "null")]),
+    ];
+    let node = parse(input).unwrap();
+    for (t, expected) in tests {
+      let actual = node.extract_values_by_type(t);
+      let actual: Vec<_> = actual.iter().map(|(p, x)| (p.as_str(), *x)).collect();
+      assert_eq!(actual, expected, "type: {:?}", t);
+    }
+
+    assert_eq!(
+      parse(r#""x""#)
+        .unwrap()
+        .extract_values_by_type(JsonType::String),
+      vec![(String::new(), r#""x""#)]
+    );
+  }
+
+  #[test]
+  fn depth() {
+    let tests = vec![
+      ("1", 0),
+      ("\"a\"", 0),
+      ("{}", 1),
+      ("[]", 1),
+      (r#"{"a": {"b": 1}}"#, 2),
+      ("[[[]]]", 3),
+      (r#"[1, {"a": [[2]]}, []]"#, 4),
+    ];
+    for (input, expected) in tests {
+      assert_eq!(parse(input).unwrap().depth(), expected, "input: {}", input);
+    }
+  }
+
+  #[test]
+  fn count() {
+    let stats = |objects, arrays, values, depth| NodeStats {
+      objects,
+      arrays,
+      values,
+      depth,
+    };
+    let tests = [
+      ("1", stats(0, 0, 1, 0)),
+      ("[]", stats(0, 1, 0, 1)),
+      (r#"{"a":1,"b":"c"}"#, stats(1, 0, 2, 1)),
+      (r#"{"a":[1,{"b":[[]]}],"c":{"d":null}}"#, stats(3, 3, 2, 5)),
+    ];
+    for (input, expected) in tests {
+      assert_eq!(parse(input).unwrap().count(), expected, "input: {}", input);
+    }
+    assert_eq!(
+      stats(1, 2, 3, 4).to_string(),
+      "{objects:1, arrays:2, values:3, depth:4}"
+    );
+  }
+
+  #[test]
+  fn count_total_characters() {
+    let tests = [
+      "1",
+      r#""é""#,
+      "[]",
+      "{}",
+      "[1]",
+      r#"{"a":1}"#,
+      r#"{"é":[1,{"b":[],"c":{}},"x"],"d":{"e":{"f":null}}}"#,
+    ];
+    for input in tests {
+      let node = parse(input).unwrap();
+      assert_eq!(
+        node.count_total_characters(),
+        node.to_string().chars().count(),
+        "input: {}",
+        input
+      );
+    }
+  }
 }
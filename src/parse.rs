@@ -1,73 +1,206 @@
-use crate::node::Node::{self, Array, Object, Value};
+use crate::{
+  export::quote_value,
+  node::Node::{self, Array, Object, Value},
+};
 use nom::{
   branch::alt,
-  bytes::complete::{tag, take_while, take_while1},
-  combinator::{map, peek},
-  error::{convert_error, VerboseError},
-  multi::{fold_many0, separated_list0},
-  sequence::{delimited, separated_pair},
+  bytes::complete::{tag, take_until, take_while, take_while1},
+  combinator::{map, peek, recognize},
+  error::VerboseError,
+  multi::{fold_many0, many0_count},
+  sequence::{delimited, preceded, separated_pair},
   Err::{Error, Failure, Incomplete},
   IResult,
 };
+use std::{borrow::Cow, fmt};
 
 pub type Result<'a, O> = IResult<&'a str, O, VerboseError<&'a str>>;
 
-pub fn parse(input: &str) -> std::result::Result<Node, String> {
-  match node()(input) {
+/// Where and why parsing failed. `offset` is in bytes, `line` and `column`
+/// count from 1, with `column` in characters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+  pub message: String,
+  pub offset: usize,
+  pub line: usize,
+  pub column: usize,
+}
+
+impl ParseError {
+  fn new(input: &str, e: VerboseError<&str>) -> Self {
+    let rest = e.errors.first().map_or("", |(rest, _)| *rest);
+    let offset = input.len() - rest.len();
+    let before = &input[..offset];
+    let line_start = before.rfind('\n').map_or(0, |x| x + 1);
+    ParseError {
+      message: match rest.chars().next() {
+        Some(x) => format!("unexpected {:?}", x),
+        None => "unexpected end of input".to_owned(),
+      },
+      offset,
+      line: before.matches('\n').count() + 1,
+      column: before[line_start..].chars().count() + 1,
+    }
+  }
+}
+
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}:{}: {}", self.line, self.column, self.message)
+  }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Extensions to JSON accepted by `parse_with_options`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ParseOptions {
+  /// Accept a comma after the last element of an array or object.
+  pub trailing_commas: bool,
+
+  /// Skip `// line` and `/* block */` comments between tokens, as in JSONC
+  /// files like VS Code settings. The comments are not kept.
+  pub comments: bool,
+
+  /// Accept object keys without quotes, like `{a: 1}`. The keys are quoted,
+  /// so the output is still JSON.
+  pub unquoted_keys: bool,
+}
+
+pub fn parse(input: &str) -> std::result::Result<Node<'_>, ParseError> {
+  parse_with_options(input, ParseOptions::default())
+}
+
+/// Like `parse`, but also accepts a trailing comma after the last element of
+/// an array or object and unquoted object keys, as often found in hand
+/// edited files.
+pub fn parse_lenient(input: &str) -> std::result::Result<Node<'_>, ParseError> {
+  parse_with_options(
+    input,
+    ParseOptions {
+      trailing_commas: true,
+      unquoted_keys: true,
+      ..Default::default()
+    },
+  )
+}
+
+pub fn parse_with_options(
+  input: &str,
+  options: ParseOptions,
+) -> std::result::Result<Node<'_>, ParseError> {
+  match node(options)(input) {
     Ok((_, node)) => Ok(node),
-    Err(Error(e)) => Err(convert_error(input, e)),
-    Err(Failure(e)) => Err(convert_error(input, e)),
+    Err(Error(e)) | Err(Failure(e)) => Err(ParseError::new(input, e)),
     Err(Incomplete(_)) => panic!("unexpected incomplete error"),
   }
 }
 
-fn node() -> impl Fn(&str) -> Result<Node> {
-  |input| ws(alt((object(), array(), value())))(input)
+fn node(options: ParseOptions) -> impl Fn(&str) -> Result<Node> {
+  // Choose by the first character instead of trying each in turn, so errors
+  // point inside the array or object instead of at its start.
+  move |input| {
+    let (input, _) = space(options)(input)?;
+    let (input, node) = match input.chars().next() {
+      Some('{') => object(options)(input)?,
+      Some('[') => array(options)(input)?,
+      _ => value(options)(input)?,
+    };
+    let (input, _) = space(options)(input)?;
+    Ok((input, node))
+  }
 }
 
-fn array() -> impl Fn(&str) -> Result<Node> {
-  |input| {
+fn array(options: ParseOptions) -> impl Fn(&str) -> Result<Node> {
+  move |input| {
     map(
       delimited(
-        ws(tag("[")),
-        separated_list0(ws(tag(",")), node()),
-        ws(tag("]")),
+        ws(options, tag("[")),
+        list(options, ']', node(options)),
+        ws(options, tag("]")),
       ),
       Array,
     )(input)
   }
 }
 
-fn object() -> impl Fn(&str) -> Result<Node> {
-  |input| {
+fn object(options: ParseOptions) -> impl Fn(&str) -> Result<Node> {
+  move |input| {
     map(
       delimited(
-        ws(tag("{")),
-        separated_list0(ws(tag(",")), separated_pair(string(), ws(tag(":")), node())),
-        ws(tag("}")),
+        ws(options, tag("{")),
+        list(
+          options,
+          '}',
+          separated_pair(key(options), ws(options, tag(":")), node(options)),
+        ),
+        ws(options, tag("}")),
       ),
       Object,
     )(input)
   }
 }
 
-fn value() -> impl Fn(&str) -> Result<Node> {
-  |input| {
+/// Parses comma separated items up to `close`, followed by an optional comma
+/// after the last one if trailing commas are allowed. An item failing after
+/// a comma is an error, rather than the end of the list.
+fn list<'a, O>(
+  options: ParseOptions,
+  close: char,
+  mut item: impl FnMut(&'a str) -> Result<'a, O> + 'a,
+) -> impl FnMut(&'a str) -> Result<'a, Vec<O>> {
+  let mut comma = ws(options, tag(","));
+  move |input| {
+    let mut xs = vec![];
+    let (mut input, _) = space(options)(input)?;
+    if input.starts_with(close) {
+      return Ok((input, xs));
+    }
+    loop {
+      let (rest, x) = item(input)?;
+      xs.push(x);
+      match comma(rest) {
+        Ok((rest, _)) if options.trailing_commas && rest.starts_with(close) => {
+          return Ok((rest, xs))
+        }
+        Ok((rest, _)) => input = rest,
+        Err(Error(_)) => return Ok((rest, xs)),
+        Err(e) => return Err(e),
+      }
+    }
+  }
+}
+
+fn value(options: ParseOptions) -> impl Fn(&str) -> Result<Node> {
+  move |input| {
     map(
       |input| {
         if peek(tag::<&str, &str, VerboseError<&str>>("\""))(input).is_ok() {
           string()(input)
         } else {
-          stringish()(input)
+          stringish(options)(input)
         }
       },
-      Value,
+      |x| Value(x.into()),
     )(input)
   }
 }
 
-fn stringish() -> impl Fn(&str) -> Result<&str> {
-  |input| take_while1(|x: char| !x.is_whitespace() && !",:{}[]".contains(x))(input)
+fn key(options: ParseOptions) -> impl Fn(&str) -> Result<Cow<str>> {
+  move |input| match options.unquoted_keys && !input.starts_with('"') {
+    true => map(stringish(options), |x| quote_value(x).into())(input),
+    false => map(string(), Cow::from)(input),
+  }
+}
+
+fn stringish(options: ParseOptions) -> impl Fn(&str) -> Result<&str> {
+  move |input| {
+    let delimiters = match options.comments {
+      true => ",:{}[]/",
+      false => ",:{}[]",
+    };
+    take_while1(|x: char| !x.is_whitespace() && !delimiters.contains(x))(input)
+  }
 }
 
 fn string() -> impl Fn(&str) -> Result<&str> {
@@ -90,13 +223,21 @@ fn string() -> impl Fn(&str) -> Result<&str> {
 }
 
 fn ws<'a, O>(
-  mut parse: impl FnMut(&'a str) -> Result<'a, O> + 'a,
+  options: ParseOptions,
+  parse: impl FnMut(&'a str) -> Result<'a, O> + 'a,
 ) -> impl FnMut(&'a str) -> Result<'a, O> {
-  delimited(space(), move |input| parse(input), space())
+  delimited(space(options), parse, space(options))
 }
 
-fn space() -> impl Fn(&str) -> Result<&str> {
-  |input| take_while(|c: char| c.is_whitespace())(input)
+fn space(options: ParseOptions) -> impl Fn(&str) -> Result<&str> {
+  move |input| match options.comments {
+    false => take_while(|c: char| c.is_whitespace())(input),
+    true => recognize(many0_count(alt((
+      take_while1(|c: char| c.is_whitespace()),
+      preceded(tag("//"), take_while(|c| c != '\n')),
+      delimited(tag("/*"), take_until("*/"), tag("*/")),
+    ))))(input),
+  }
 }
 
 #[cfg(test)]
@@ -106,34 +247,34 @@ mod tests {
   #[test]
   fn parse() {
     let tests = vec![
-      ("true", Value("true")),
-      (" true", Value("true")),
-      (" true ", Value("true")),
-      ("true ", Value("true")),
-      ("false\t", Value("false")),
-      ("\nfalse\t", Value("false")),
-      ("null", Value("null")),
-      ("1", Value("1")),
-      ("-2", Value("-2")),
-      ("-3.4", Value("-3.4")),
-      ("5e6", Value("5e6")),
-      ("7.00", Value("7.00")),
-      ("-8.900", Value("-8.900")),
-      (" -10", Value("-10")),
-      (" 11 ", Value("11")),
-      ("12\t", Value("12")),
-      ("\n\t13\n", Value("13")),
-      ("\"\"", Value("\"\"")),
-      (" \"\"", Value("\"\"")),
-      (" \"\" ", Value("\"\"")),
-      ("\"\" ", Value("\"\"")),
-      (" \" \" ", Value("\" \"")),
-      (" \"a b\" ", Value("\"a b\"")),
-      (" \"\\\"a b\" ", Value("\"\\\"a b\"")),
-      (" \"a\\\" b\" ", Value("\"a\\\" b\"")),
-      (" \"a b\\\"\" ", Value("\"a b\\\"\"")),
-      (" \"a\nb\" ", Value("\"a\nb\"")),
-      (" \"\ta \nb false\" ", Value("\"\ta \nb false\"")),
+      ("true", Value("true".into())),
+      (" true", Value("true".into())),
+      (" true ", Value("true".into())),
+      ("true ", Value("true".into())),
+      ("false\t", Value("false".into())),
+      ("\nfalse\t", Value("false".into())),
+      ("null", Value("null".into())),
+      ("1", Value("1".into())),
+      ("-2", Value("-2".into())),
+      ("-3.4", Value("-3.4".into())),
+      ("5e6", Value("5e6".into())),
+      ("7.00", Value("7.00".into())),
+      ("-8.900", Value("-8.900".into())),
+      (" -10", Value("-10".into())),
+      (" 11 ", Value("11".into())),
+      ("12\t", Value("12".into())),
+      ("\n\t13\n", Value("13".into())),
+      ("\"\"", Value("\"\"".into())),
+      (" \"\"", Value("\"\"".into())),
+      (" \"\" ", Value("\"\"".into())),
+      ("\"\" ", Value("\"\"".into())),
+      (" \" \" ", Value("\" \"".into())),
+      (" \"a b\" ", Value("\"a b\"".into())),
+      (" \"\\\"a b\" ", Value("\"\\\"a b\"".into())),
+      (" \"a\\\" b\" ", Value("\"a\\\" b\"".into())),
+      (" \"a b\\\"\" ", Value("\"a b\\\"\"".into())),
+      (" \"a\nb\" ", Value("\"a\nb\"".into())),
+      (" \"\ta \nb false\" ", Value("\"\ta \nb false\"".into())),
       ("[]", Array(vec![])),
       (" []", Array(vec![])),
       (" [] ", Array(vec![])),
@@ -146,15 +287,22 @@ mod tests {
       (" { } ", Object(vec![])),
       (" {} ", Object(vec![])),
       (" {}", Object(vec![])),
-      ("[1] ", Array(vec![Value("1")])),
-      ("[ 1, false] ", Array(vec![Value("1"), Value("false")])),
+      ("[1] ", Array(vec![Value("1".into())])),
+      (
+        "[ 1, false] ",
+        Array(vec![Value("1".into()), Value("false".into())]),
+      ),
       (
         "[ 0E-18 , true ] ",
-        Array(vec![Value("0E-18"), Value("true")]),
+        Array(vec![Value("0E-18".into()), Value("true".into())]),
       ),
       (
         "[ 2 , true , {}] ",
-        Array(vec![Value("2"), Value("true"), Object(vec![])]),
+        Array(vec![
+          Value("2".into()),
+          Value("true".into()),
+          Object(vec![]),
+        ]),
       ),
       (
         "[\t{},{} , {} , {}\n, []] ",
@@ -166,23 +314,29 @@ mod tests {
           Array(vec![]),
         ]),
       ),
-      ("{\"hi\" : true} ", Object(vec![("\"hi\"", Value("true"))])),
+      (
+        "{\"hi\" : true} ",
+        Object(vec![("\"hi\"".into(), Value("true".into()))]),
+      ),
       (
         "{\"hello world\" : {}} ",
-        Object(vec![("\"hello world\"", Object(vec![]))]),
+        Object(vec![("\"hello world\"".into(), Object(vec![]))]),
+      ),
+      (
+        "{\"bob\" : []} ",
+        Object(vec![("\"bob\"".into(), Array(vec![]))]),
       ),
-      ("{\"bob\" : []} ", Object(vec![("\"bob\"", Array(vec![]))])),
       (
         "{\"bob\" : { \"ja\tck\": [1, -3, true, {\"a\" : false}]}} ",
         Object(vec![(
-          "\"bob\"",
+          "\"bob\"".into(),
           Object(vec![(
-            "\"ja\tck\"",
+            "\"ja\tck\"".into(),
             Array(vec![
-              Value("1"),
-              Value("-3"),
-              Value("true"),
-              Object(vec![("\"a\"", Value("false"))]),
+              Value("1".into()),
+              Value("-3".into()),
+              Value("true".into()),
+              Object(vec![("\"a\"".into(), Value("false".into()))]),
             ]),
           )]),
         )]),
@@ -190,23 +344,23 @@ mod tests {
       (
         "[ 10.000000 , null, { \"ja\tck\": [1, -3, true, {\"a\" : false}]} ]",
         Array(vec![
-          Value("10.000000"),
-          Value("null"),
+          Value("10.000000".into()),
+          Value("null".into()),
           Object(vec![(
-            "\"ja\tck\"",
+            "\"ja\tck\"".into(),
             Array(vec![
-              Value("1"),
-              Value("-3"),
-              Value("true"),
-              Object(vec![("\"a\"", Value("false"))]),
+              Value("1".into()),
+              Value("-3".into()),
+              Value("true".into()),
+              Object(vec![("\"a\"".into(), Value("false".into()))]),
             ]),
           )]),
         ]),
       ),
-      ("\\u001b\\u007f", Value("\\u001b\\u007f")),
+      ("\\u001b\\u007f", Value("\\u001b\\u007f".into())),
       (
         "\"^[^@]+@[^@.]+\\.[^@]+$\"",
-        Value("\"^[^@]+@[^@.]+\\.[^@]+$\""),
+        Value("\"^[^@]+@[^@.]+\\.[^@]+$\"".into()),
       ),
     ];
 
@@ -222,4 +376,104 @@ mod tests {
       );
     }
   }
+
+  #[test]
+  fn parse_error() {
+    let error = |offset, line, column, message: &str| ParseError {
+      message: message.to_owned(),
+      offset,
+      line,
+      column,
+    };
+    let tests = vec![
+      ("", error(0, 1, 1, "unexpected end of input")),
+      ("[1,]", error(3, 1, 4, "unexpected ']'")),
+      (
+        "{\n  \"a\": 1\n  \"b\": 2\n}",
+        error(13, 3, 3, "unexpected '\"'"),
+      ),
+      ("{\"é\": [1 2]}", error(10, 1, 10, "unexpected '2'")),
+    ];
+
+    for (input, expected) in tests {
+      assert_eq!(super::parse(input), Err(expected), "input: {}", input);
+    }
+    assert_eq!(
+      error(3, 2, 1, "unexpected ']'").to_string(),
+      "2:1: unexpected ']'"
+    );
+  }
+
+  #[test]
+  fn parse_lenient() {
+    let tests = vec![
+      ("[1,]", Ok(Array(vec![Value("1".into())]))),
+      (
+        "[ 1 , 2 ,\n]",
+        Ok(Array(vec![Value("1".into()), Value("2".into())])),
+      ),
+      (
+        "{\"a\": [{},], }",
+        Ok(Object(vec![("\"a\"".into(), Array(vec![Object(vec![])]))])),
+      ),
+      ("[]", Ok(Array(vec![]))),
+      (
+        "{b: 1, \"a\": 2, c\"d: 3}",
+        Ok(Object(vec![
+          ("\"b\"".into(), Value("1".into())),
+          ("\"a\"".into(), Value("2".into())),
+          ("\"c\\\"d\"".into(), Value("3".into())),
+        ])),
+      ),
+      ("{: 1}", Err(())),
+      ("[,]", Err(())),
+      ("{,}", Err(())),
+      ("[1,,]", Err(())),
+    ];
+
+    for (input, expected) in tests {
+      let actual = super::parse_lenient(input);
+      assert_eq!(
+        actual.as_ref().map_err(|_| &()),
+        expected.as_ref(),
+        "input: {}",
+        input
+      );
+    }
+    assert!(super::parse("[1,]").is_err());
+    assert!(super::parse("{\"a\":1,}").is_err());
+    assert!(super::parse("{a:1}").is_err());
+  }
+
+  #[test]
+  fn parse_comments() {
+    let options = ParseOptions {
+      comments: true,
+      ..Default::default()
+    };
+    let tests = vec![
+      ("// top\n1 // end", Ok(Value("1".into()))),
+      ("[1/* inline */,2]", Ok(Array(vec![Value("1".into()), Value("2".into())]))),
+      (
+        "{\n  // a comment\n  \"a\": /* b */ \"//c\", // end of line\n  /*\n   * block\n   */\n  \"d\": \"/* e */\"\n}",
+        Ok(Object(vec![
+          ("\"a\"".into(), Value("\"//c\"".into())),
+          ("\"d\"".into(), Value("\"/* e */\"".into())),
+        ])),
+      ),
+      ("/**/{}//", Ok(Object(vec![]))),
+      ("[1 /* unterminated ]", Err(())),
+    ];
+
+    for (input, expected) in tests {
+      let actual = super::parse_with_options(input, options);
+      assert_eq!(
+        actual.as_ref().map_err(|_| &()),
+        expected.as_ref(),
+        "input: {}",
+        input
+      );
+    }
+    assert!(super::parse("[1 // x\n]").is_err());
+  }
 }
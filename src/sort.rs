@@ -1,250 +1,2229 @@
-use crate::node::Node::{self, Array, Object, Value};
-use std::cmp::Ordering;
+use crate::{
+  export::quote_value,
+  node::{
+    pointer_token,
+    Node::{self, Array, Object, Value},
+  },
+};
+#[cfg(feature = "icu")]
+use icu_collator::{Collator, CollatorOptions};
+#[cfg(feature = "icu")]
+use icu_locid::Locale;
+#[cfg(feature = "random")]
+use rand::{seq::SliceRandom, Rng};
+use semver::Version;
+use std::{borrow::Cow, cmp::Ordering, fmt};
+#[cfg(feature = "xxhash")]
+use xxhash_rust::xxh3::xxh3_64;
+
+pub type SortKeyTransform = Box<dyn Fn(&str) -> String>;
+
+/// Transformations applied in order to an object key to derive the key it is
+/// sorted by.
+#[derive(Default)]
+pub struct SortKeyPipeline(pub Vec<SortKeyTransform>);
+
+impl SortKeyPipeline {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn then(mut self, f: impl Fn(&str) -> String + 'static) -> Self {
+    self.0.push(Box::new(f));
+    self
+  }
+
+  pub fn apply(&self, key: &str) -> String {
+    self.0.iter().fold(key.to_owned(), |key, f| f(&key))
+  }
+}
+
+/// Maps a key spelling a JSON literal in any case, like `"FALSE"`, to the
+/// literal in lower case, for use in a `SortKeyPipeline`.
+pub fn normalize_literal(key: &str) -> String {
+  match key.to_lowercase().as_str() {
+    x @ ("null" | "true" | "false") => x.to_owned(),
+    _ => key.to_owned(),
+  }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SortOptions {
+  /// Sort in descending instead of ascending order.
+  pub reverse: bool,
+
+  /// Compare object keys by their lowercase form, so `B` no longer sorts
+  /// before `a`. Keys that differ only in case keep their bytewise order.
+  pub case_insensitive: bool,
+
+  /// How `sort_by_value` compares values.
+  pub mode: SortMode,
+
+  /// Where `sort_by_value` puts objects without the key.
+  pub missing_key: MissingKeyPosition,
+
+  /// Object keys to put first, in this order, when sorting by name. The
+  /// other keys follow, sorted as usual.
+  pub priority: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum MissingKeyPosition {
+  /// Objects without the key compare equal to all others, so they stay
+  /// around their original position.
+  #[default]
+  Unspecified,
+  First,
+  Last,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SortMode {
+  /// Numbers, including numeric strings, by value and the rest as text.
+  #[default]
+  Natural,
+  /// Semantic versions like `"v1.10.0"` in version order, ignoring a leading
+  /// `v`. Values that are not versions are compared as text.
+  VersionString,
+}
+
+impl SortOptions {
+  fn compare_names(&self, a: &str, b: &str) -> Ordering {
+    let rank = |key: &str| self.priority.iter().position(|x| x == key);
+    match (rank(a), rank(b)) {
+      (Some(x), Some(y)) => return x.cmp(&y),
+      (Some(_), None) => return Ordering::Less,
+      (None, Some(_)) => return Ordering::Greater,
+      (None, None) => {}
+    }
+    let ordering = match self.case_insensitive {
+      // Lowercase as we go instead of with `to_lowercase`, which would
+      // allocate two strings per comparison.
+      true => lowercase(a).cmp(lowercase(b)).then_with(|| a.cmp(b)),
+      false => a.cmp(b),
+    };
+    self.apply(ordering)
+  }
+
+  fn compare_at_path(&self, a: &Node, b: &Node, path: &[&str]) -> Ordering {
+    self.compare_found(
+      SortValue::at(a, path).as_ref(),
+      SortValue::at(b, path).as_ref(),
+    )
+  }
+
+  /// Compares the values found at a sort path, if any.
+  fn compare_found(&self, a: Option<&SortValue>, b: Option<&SortValue>) -> Ordering {
+    match (a, b) {
+      (Some(a), Some(b)) => self.apply(match self.mode {
+        SortMode::Natural => compare_numbers_first(a.number, b.number, a.raw, b.raw),
+        SortMode::VersionString => compare_versions(a.raw, b.raw),
+      }),
+      (None, None) => Ordering::Equal,
+      (a, _) => match (self.missing_key, a) {
+        (MissingKeyPosition::Unspecified, _) => Ordering::Equal,
+        (MissingKeyPosition::First, None) | (MissingKeyPosition::Last, Some(_)) => Ordering::Less,
+        (MissingKeyPosition::First, Some(_)) | (MissingKeyPosition::Last, None) => {
+          Ordering::Greater
+        }
+      },
+    }
+  }
+
+  fn apply(&self, ordering: Ordering) -> Ordering {
+    match self.reverse {
+      true => ordering.reverse(),
+      false => ordering,
+    }
+  }
+}
+
+#[cfg(feature = "icu")]
+#[derive(Debug, PartialEq)]
+pub enum LocaleError {
+  Invalid(String),
+  Unsupported(String),
+}
+
+#[cfg(feature = "icu")]
+impl fmt::Display for LocaleError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      LocaleError::Invalid(x) => write!(f, "invalid locale: {}", x),
+      LocaleError::Unsupported(x) => write!(f, "no collation data for locale: {}", x),
+    }
+  }
+}
+
+#[cfg(feature = "icu")]
+impl std::error::Error for LocaleError {}
+
+/// A value found at a sort path, with its number parsed once instead of on
+/// every comparison.
+struct SortValue<'a> {
+  raw: &'a str,
+  number: Option<f64>,
+}
+
+impl<'a> SortValue<'a> {
+  fn at(node: &'a Node, path: &[&str]) -> Option<Self> {
+    find_value_path(node, path).map(|raw| SortValue {
+      raw,
+      number: number(raw),
+    })
+  }
+}
+
+fn lowercase(s: &str) -> impl Iterator<Item = char> + '_ {
+  s.chars().flat_map(char::to_lowercase)
+}
+
+/// A run of `verify_sort_by_name` whose output differed from the first.
+#[derive(Debug, PartialEq)]
+pub struct SortMismatch {
+  pub run: usize,
+  pub line: usize,
+  pub expected: String,
+  pub actual: String,
+}
+
+impl fmt::Display for SortMismatch {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "sort run {} differs at line {}:\n- {}\n+ {}",
+      self.run, self.line, self.expected, self.actual
+    )
+  }
+}
+
+/// An object entry moved by `sort_by_name_with_diff`. `key` is the JSON
+/// Pointer of the entry.
+#[derive(Debug, PartialEq)]
+pub struct KeyMove {
+  pub key: String,
+  pub from: usize,
+  pub to: usize,
+}
+
+impl fmt::Display for KeyMove {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "moved \"{}\" from position {} to position {}",
+      self.key, self.from, self.to
+    )
+  }
+}
+
+/// The first out of order pair found by `assert_sorted_by_name` or
+/// `assert_sorted_by_value`. `path` is the JSON Pointer of the object or
+/// array, and `key_before` and `key_after` are the adjacent keys, or values
+/// for arrays, that are in the wrong order.
+#[derive(Debug, PartialEq)]
+pub struct SortError {
+  pub path: String,
+  pub key_before: String,
+  pub key_after: String,
+}
+
+impl fmt::Display for SortError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "\"{}\": {} comes before {}",
+      self.path, self.key_before, self.key_after
+    )
+  }
+}
+
+impl std::error::Error for SortError {}
 
 impl Node<'_> {
-  pub fn sort_by_name(&mut self) {
+  /// Sorts `runs` copies of this node by name, each with its keys first
+  /// put in a different order, and checks that they all format the same.
+  /// Objects with duplicate keys are one way for this to fail.
+  pub fn verify_sort_by_name(&self, runs: usize) -> Result<(), SortMismatch> {
+    let mut first = self.clone();
+    first.sort_by_name(&SortOptions::default());
+    let expected = first.to_string();
+    for run in 1..=runs {
+      let mut node = self.clone();
+      node.permute_keys(run);
+      node.sort_by_name(&SortOptions::default());
+      let actual = node.to_string();
+      if actual != expected {
+        let (line, expected, actual) = first_different_line(&expected, &actual);
+        return Err(SortMismatch {
+          run,
+          line,
+          expected: expected.to_owned(),
+          actual: actual.to_owned(),
+        });
+      }
+    }
+    Ok(())
+  }
+
+  /// Reorders the keys of objects differently for each `run`.
+  fn permute_keys(&mut self, run: usize) {
     match self {
       Value(_) => {}
       Object(xs) => {
-        xs.iter_mut().for_each(|(_, x)| x.sort_by_name());
-        xs.sort_by_key(|x| unquote(x.0));
+        xs.iter_mut().for_each(|(_, x)| x.permute_keys(run));
+        let len = xs.len();
+        if run % 2 == 1 {
+          xs.reverse();
+        } else if len > 0 {
+          xs.rotate_left(run / 2 % len);
+        }
       }
-      Array(xs) => xs.iter_mut().for_each(Self::sort_by_name),
+      Array(xs) => xs.iter_mut().for_each(|x| x.permute_keys(run)),
     }
   }
 
-  pub fn sort_by_value(&mut self, name: &str) {
+  /// Sorts objects by their unquoted key names. Names are compared by
+  /// Unicode code point, so `"Z"` sorts before `"a"`, and emoji, being high
+  /// code points, sort after letters.
+  pub fn sort_by_name(&mut self, options: &SortOptions) {
     match self {
       Value(_) => {}
-      Object(xs) => xs.iter_mut().for_each(|(_, x)| x.sort_by_value(name)),
-      Array(xs) => {
-        xs.iter_mut().for_each(|x| x.sort_by_value(name));
-        xs.sort_by(|a, b| {
-          if let (Some(a), Some(b)) = (find_value(a, &name), find_value(b, &name)) {
-            return unquote(a).cmp(unquote(b));
+      Object(xs) => {
+        xs.iter_mut().for_each(|(_, x)| x.sort_by_name(options));
+        let cmp = |a: &str, b: &str| options.compare_names(unquote(a), unquote(b));
+        // Skip the sort when the keys are already in order, which is common
+        // for sorted files that had a few keys added. This includes objects
+        // with no or one key.
+        if !xs.windows(2).all(|w| cmp(&w[0].0, &w[1].0).is_le()) {
+          xs.sort_by(|a, b| cmp(&a.0, &b.0));
+        }
+      }
+      Array(xs) => xs.iter_mut().for_each(|x| x.sort_by_name(options)),
+    }
+  }
+
+  /// Sorts like `sort_by_name` and returns the entries that changed
+  /// position, parents before children.
+  pub fn sort_by_name_with_diff(&mut self, options: &SortOptions) -> Vec<KeyMove> {
+    let mut moves = vec![];
+    self.sort_by_name_with_diff_at("", options, &mut moves);
+    moves
+  }
+
+  fn sort_by_name_with_diff_at(
+    &mut self,
+    path: &str,
+    options: &SortOptions,
+    moves: &mut Vec<KeyMove>,
+  ) {
+    match self {
+      Value(_) => {}
+      Object(xs) => {
+        let mut entries = std::mem::take(xs)
+          .into_iter()
+          .enumerate()
+          .collect::<Vec<_>>();
+        entries.sort_by(|(_, a), (_, b)| options.compare_names(unquote(&a.0), unquote(&b.0)));
+        for (to, (from, (key, x))) in entries.into_iter().enumerate() {
+          let path = format!("{}/{}", path, pointer_token(unquote(&key)));
+          if from != to {
+            moves.push(KeyMove {
+              key: path.clone(),
+              from,
+              to,
+            });
           }
-          return Ordering::Equal;
+          xs.push((key, x));
+        }
+        for (key, x) in xs.iter_mut() {
+          let path = format!("{}/{}", path, pointer_token(unquote(key)));
+          x.sort_by_name_with_diff_at(&path, options, moves);
+        }
+      }
+      Array(xs) => {
+        for (i, x) in xs.iter_mut().enumerate() {
+          x.sort_by_name_with_diff_at(&format!("{}/{}", path, i), options, moves);
+        }
+      }
+    }
+  }
+
+  /// Sorts objects by name, except that keys in `priority` come first, in
+  /// the order given.
+  pub fn sort_by_name_with_priority(&mut self, priority: &[&str]) {
+    self.sort_by_name(&SortOptions {
+      priority: priority.iter().map(|x| x.to_string()).collect(),
+      ..Default::default()
+    });
+  }
+
+  /// Sorts objects by name, except that an object with a `hint_key` entry
+  /// holding an array of key names, like `"__order":["b","a"]`, puts those
+  /// keys first, in that order. The `hint_key` entries are removed.
+  pub fn sort_by_name_with_order_hint(&mut self, hint_key: &str) {
+    match self {
+      Value(_) => {}
+      Object(xs) => {
+        let hint = xs
+          .iter()
+          .position(|(key, _)| unquote(key) == hint_key)
+          .map(|i| xs.remove(i).1);
+        xs.retain(|(key, _)| unquote(key) != hint_key);
+        xs.iter_mut()
+          .for_each(|(_, x)| x.sort_by_name_with_order_hint(hint_key));
+        let priority = match &hint {
+          Some(Array(names)) => names
+            .iter()
+            .filter_map(|x| match x {
+              Value(x) if x.starts_with('"') => Some(unquote(x)),
+              _ => None,
+            })
+            .collect(),
+          _ => vec![],
+        };
+        sort_entries_with_priority(xs, &priority);
+      }
+      Array(xs) => xs
+        .iter_mut()
+        .for_each(|x| x.sort_by_name_with_order_hint(hint_key)),
+    }
+  }
+
+  /// Sorts objects by name in ascending order at even depths and descending
+  /// order at odd depths, starting from `depth` at this node. Every object
+  /// or array nesting level counts as one depth.
+  pub fn sort_by_name_alternating(&mut self, depth: usize) {
+    match self {
+      Value(_) => {}
+      Object(xs) => {
+        xs.iter_mut()
+          .for_each(|(_, x)| x.sort_by_name_alternating(depth + 1));
+        if depth.is_multiple_of(2) {
+          xs.sort_by(|a, b| unquote(&a.0).cmp(unquote(&b.0)));
+        } else {
+          xs.sort_by(|a, b| unquote(&b.0).cmp(unquote(&a.0)));
+        }
+      }
+      Array(xs) => xs
+        .iter_mut()
+        .for_each(|x| x.sort_by_name_alternating(depth + 1)),
+    }
+  }
+
+  pub fn sort_by_name_piped(&mut self, pipeline: &SortKeyPipeline) {
+    match self {
+      Value(_) => {}
+      Object(xs) => {
+        xs.iter_mut()
+          .for_each(|(_, x)| x.sort_by_name_piped(pipeline));
+        xs.sort_by_cached_key(|x| pipeline.apply(&x.0));
+      }
+      Array(xs) => xs.iter_mut().for_each(|x| x.sort_by_name_piped(pipeline)),
+    }
+  }
+
+  /// Sorts objects by their unquoted key names, ordered by `compare`.
+  pub fn sort_by_name_with_comparator(&mut self, compare: &impl Fn(&str, &str) -> Ordering) {
+    match self {
+      Value(_) => {}
+      Object(xs) => {
+        xs.iter_mut()
+          .for_each(|(_, x)| x.sort_by_name_with_comparator(compare));
+        xs.sort_by(|(a, _), (b, _)| compare(unquote(a), unquote(b)));
+      }
+      Array(xs) => xs
+        .iter_mut()
+        .for_each(|x| x.sort_by_name_with_comparator(compare)),
+    }
+  }
+
+  /// Sorts objects by name, except that an entry holding an array of
+  /// objects sorts by the key set of the array's first object, e.g.
+  /// `"rules":[{"name":"z"}]` sorts as `name`.
+  pub fn sort_by_name_arrays_as_block(&mut self) {
+    match self {
+      Value(_) => {}
+      Object(xs) => {
+        xs.iter_mut()
+          .for_each(|(_, x)| x.sort_by_name_arrays_as_block());
+        xs.sort_by_cached_key(|(key, x)| match x {
+          Array(ys) => match ys.first() {
+            Some(Object(zs)) => {
+              let mut keys = zs.iter().map(|(k, _)| unquote(k)).collect::<Vec<_>>();
+              keys.sort();
+              keys.join(",")
+            }
+            _ => unquote(key).to_owned(),
+          },
+          _ => unquote(key).to_owned(),
+        });
+      }
+      Array(xs) => xs.iter_mut().for_each(Self::sort_by_name_arrays_as_block),
+    }
+  }
+
+  /// Shuffles the keys of objects into a random order, for checking that
+  /// consumers do not depend on key order.
+  #[cfg(feature = "random")]
+  pub fn shuffle_keys(&mut self, rng: &mut impl Rng) {
+    match self {
+      Value(_) => {}
+      Object(xs) => {
+        xs.iter_mut().for_each(|(_, x)| x.shuffle_keys(rng));
+        xs.shuffle(rng);
+      }
+      Array(xs) => xs.iter_mut().for_each(|x| x.shuffle_keys(rng)),
+    }
+  }
+
+  /// Sorts objects by the xxHash of their unquoted key names. The order is
+  /// the same on every platform but is not meaningful to a reader.
+  #[cfg(feature = "xxhash")]
+  pub fn sort_by_name_hash_order(&mut self) {
+    match self {
+      Value(_) => {}
+      Object(xs) => {
+        xs.iter_mut().for_each(|(_, x)| x.sort_by_name_hash_order());
+        xs.sort_by_cached_key(|(key, _)| {
+          let key = unquote(key);
+          (xxh3_64(key.as_bytes()), key.to_owned())
+        });
+      }
+      Array(xs) => xs.iter_mut().for_each(Self::sort_by_name_hash_order),
+    }
+  }
+
+  /// Sorts objects by name using the ICU collation rules of `locale`, a BCP
+  /// 47 tag like `de-DE`. Collation variants are selected with Unicode
+  /// extensions, e.g. `zh-u-co-stroke` for stroke order, as plain `zh-CN`
+  /// sorts by pinyin.
+  #[cfg(feature = "icu")]
+  pub fn sort_by_name_with_locale(&mut self, locale: &str) -> Result<(), LocaleError> {
+    let parsed = locale
+      .parse::<Locale>()
+      .map_err(|_| LocaleError::Invalid(locale.to_owned()))?;
+    let collator = Collator::try_new(&(&parsed).into(), CollatorOptions::new())
+      .map_err(|_| LocaleError::Unsupported(locale.to_owned()))?;
+    self.sort_by_collator(&collator);
+    Ok(())
+  }
+
+  #[cfg(feature = "icu")]
+  fn sort_by_collator(&mut self, collator: &Collator) {
+    match self {
+      Value(_) => {}
+      Object(xs) => {
+        xs.iter_mut()
+          .for_each(|(_, x)| x.sort_by_collator(collator));
+        xs.sort_by(|a, b| collator.compare(unquote(&a.0), unquote(&b.0)));
+      }
+      Array(xs) => xs.iter_mut().for_each(|x| x.sort_by_collator(collator)),
+    }
+  }
+
+  /// Sorts objects in the order their keys are listed in the `properties` of
+  /// the matching JSON Schema, following `properties` into nested objects and
+  /// `items` into arrays. Keys missing from the schema come last, sorted by
+  /// name.
+  pub fn sort_by_schema(&mut self, schema: &Node) {
+    self.sort_by_schema_node(Some(schema));
+  }
+
+  fn sort_by_schema_node(&mut self, schema: Option<&Node>) {
+    match self {
+      Value(_) => {}
+      Object(xs) => {
+        let properties = schema.and_then(|x| x.get("properties"));
+        xs.iter_mut()
+          .for_each(|(key, x)| x.sort_by_schema_node(properties.and_then(|x| x.get(unquote(key)))));
+        let priority = match properties {
+          Some(Object(ys)) => ys.iter().map(|(key, _)| unquote(key)).collect(),
+          _ => vec![],
+        };
+        sort_entries_with_priority(xs, &priority);
+      }
+      Array(xs) => {
+        let items = schema.and_then(|x| x.get("items"));
+        xs.iter_mut().for_each(|x| x.sort_by_schema_node(items));
+      }
+    }
+  }
+
+  /// Returns whether every object in the tree already has its keys in the
+  /// order `sort_by_name` would produce.
+  pub fn is_sorted_by_name(&self) -> bool {
+    match self {
+      Value(_) => true,
+      Object(xs) => {
+        xs.windows(2).all(|w| unquote(&w[0].0) <= unquote(&w[1].0))
+          && xs.iter().all(|(_, x)| x.is_sorted_by_name())
+      }
+      Array(xs) => xs.iter().all(Self::is_sorted_by_name),
+    }
+  }
+
+  /// Like `is_sorted_by_name`, but returns the first pair of keys out of
+  /// order, for use in tests.
+  pub fn assert_sorted_by_name(&self) -> Result<(), SortError> {
+    self.assert_sorted_at("", &|xs| match xs {
+      Object(xs) => xs
+        .windows(2)
+        .find(|w| unquote(&w[0].0) > unquote(&w[1].0))
+        .map(|w| (w[0].0.to_string(), w[1].0.to_string())),
+      _ => None,
+    })
+  }
+
+  /// Returns the first pair of objects in an array that `sort_by_value` with
+  /// default options would swap, identified by their values at `key`.
+  pub fn assert_sorted_by_value(&self, key: &str) -> Result<(), SortError> {
+    let options = SortOptions::default();
+    self.assert_sorted_at("", &|xs| match xs {
+      Array(xs) => xs
+        .windows(2)
+        .find(|w| options.compare_at_path(&w[0], &w[1], &[key]) == Ordering::Greater)
+        .map(|w| {
+          let value = |x| find_value(x, key).unwrap_or_default().to_owned();
+          (value(&w[0]), value(&w[1]))
+        }),
+      _ => None,
+    })
+  }
+
+  /// Walks the tree parents first, returning the first pair `unsorted`
+  /// finds.
+  fn assert_sorted_at(
+    &self,
+    path: &str,
+    unsorted: &impl Fn(&Node) -> Option<(String, String)>,
+  ) -> Result<(), SortError> {
+    if let Some((key_before, key_after)) = unsorted(self) {
+      return Err(SortError {
+        path: path.to_owned(),
+        key_before,
+        key_after,
+      });
+    }
+    match self {
+      Value(_) => Ok(()),
+      Object(xs) => xs.iter().try_for_each(|(key, x)| {
+        x.assert_sorted_at(
+          &format!("{}/{}", path, pointer_token(unquote(key))),
+          unsorted,
+        )
+      }),
+      Array(xs) => xs
+        .iter()
+        .enumerate()
+        .try_for_each(|(i, x)| x.assert_sorted_at(&format!("{}/{}", path, i), unsorted)),
+    }
+  }
+
+  /// Returns the keys of the top-level object, or of each object in a
+  /// top-level array, in their current order, as JSON strings.
+  pub fn emit_key_order(&self) -> Vec<Vec<Cow<'_, str>>> {
+    fn keys<'s>(xs: &'s [(Cow<str>, Node)]) -> Vec<Cow<'s, str>> {
+      xs.iter()
+        .map(|(key, _)| match key.starts_with('"') {
+          true => Cow::Borrowed(key.as_ref()),
+          false => Cow::Owned(quote_value(key)),
+        })
+        .collect()
+    }
+    match self {
+      Value(_) => vec![],
+      Object(xs) => vec![keys(xs)],
+      Array(xs) => xs
+        .iter()
+        .filter_map(|x| match x {
+          Object(xs) => Some(keys(xs)),
+          _ => None,
+        })
+        .collect(),
+    }
+  }
+
+  /// Sorts objects by name and object arrays by the values of `value_key`,
+  /// at every depth.
+  pub fn sort_by_value_and_name(&mut self, value_key: &str) {
+    self.sort_by_name(&SortOptions::default());
+    self.sort_by_value(value_key, &SortOptions::default());
+  }
+
+  /// Sorts objects by name, then sorts object arrays by the key names of
+  /// their objects, breaking ties with the values of `secondary_key`.
+  pub fn sort_by_name_then_value(&mut self, secondary_key: &str) {
+    self.sort_by_name(&SortOptions::default());
+    self.sort_by_key_names_then_value(secondary_key);
+  }
+
+  fn sort_by_key_names_then_value(&mut self, secondary_key: &str) {
+    fn key_names<'a>(node: &'a Node) -> Option<Vec<&'a str>> {
+      match node {
+        Object(xs) => Some(xs.iter().map(|(key, _)| unquote(key)).collect()),
+        _ => None,
+      }
+    }
+
+    match self {
+      Value(_) => {}
+      Object(xs) => xs
+        .iter_mut()
+        .for_each(|(_, x)| x.sort_by_key_names_then_value(secondary_key)),
+      Array(xs) => {
+        xs.iter_mut()
+          .for_each(|x| x.sort_by_key_names_then_value(secondary_key));
+        xs.sort_by(|a, b| match (key_names(a), key_names(b)) {
+          (Some(x), Some(y)) => x.cmp(&y).then_with(|| {
+            match (find_value(a, secondary_key), find_value(b, secondary_key)) {
+              (Some(a), Some(b)) => compare_unescaped(a, b),
+              _ => Ordering::Equal,
+            }
+          }),
+          _ => Ordering::Equal,
+        })
+      }
+    }
+  }
+
+  /// Sorts object arrays by the values of `name` like `sort_by_value`, but
+  /// groups the values by type first, see `compare_typed`. Objects without
+  /// `name` go last.
+  pub fn sort_by_type_then_value(&mut self, name: &str) {
+    match self {
+      Value(_) => {}
+      Object(xs) => xs
+        .iter_mut()
+        .for_each(|(_, x)| x.sort_by_type_then_value(name)),
+      Array(xs) => {
+        xs.iter_mut().for_each(|x| x.sort_by_type_then_value(name));
+        xs.sort_by(|a, b| match (a.get(name), b.get(name)) {
+          (Some(a), Some(b)) => compare_typed(a, b),
+          (a, b) => b.is_some().cmp(&a.is_some()),
         })
       }
     }
   }
+
+  pub fn sort_by_value(&mut self, name: &str, options: &SortOptions) {
+    self.sort_by_values(&[&[name]], options);
+  }
+
+  /// Sorts object arrays by the values at the first key path in `paths`,
+  /// using the following paths to break ties. A path like `["address",
+  /// "city"]` looks up `city` in the object under `address`.
+  pub fn sort_by_values(&mut self, paths: &[&[&str]], options: &SortOptions) {
+    match self {
+      Value(_) => {}
+      Object(xs) => xs
+        .iter_mut()
+        .for_each(|(_, x)| x.sort_by_values(paths, options)),
+      Array(xs) => {
+        xs.iter_mut().for_each(|x| x.sort_by_values(paths, options));
+        let Some((first, rest)) = paths.split_first() else {
+          return;
+        };
+        // Look up and parse the values once per element, not per comparison,
+        // keeping the first next to each index for fewer cache misses.
+        let mut keyed = xs
+          .iter()
+          .enumerate()
+          .map(|(i, x)| (SortValue::at(x, first), i))
+          .collect::<Vec<_>>();
+        let rest_values = xs
+          .iter()
+          .map(|x| {
+            rest
+              .iter()
+              .map(|path| SortValue::at(x, path))
+              .collect::<Vec<_>>()
+          })
+          .collect::<Vec<_>>();
+        keyed.sort_by(|(a, i), (b, j)| {
+          options.compare_found(a.as_ref(), b.as_ref()).then_with(|| {
+            rest_values[*i].iter().zip(&rest_values[*j]).fold(
+              Ordering::Equal,
+              |ordering, (a, b)| {
+                ordering.then_with(|| options.compare_found(a.as_ref(), b.as_ref()))
+              },
+            )
+          })
+        });
+        let order = keyed.into_iter().map(|(_, i)| i).collect::<Vec<_>>();
+        let mut taken = std::mem::take(xs).into_iter().map(Some).collect::<Vec<_>>();
+        *xs = order
+          .into_iter()
+          .map(|i| taken[i].take().expect("each index once"))
+          .collect();
+      }
+    }
+  }
+
+  /// Sorts arrays whose elements are all values, comparing numbers
+  /// numerically. Arrays holding objects or arrays keep their order.
+  pub fn sort_arrays(&mut self) {
+    match self {
+      Value(_) => {}
+      Object(xs) => xs.iter_mut().for_each(|(_, x)| x.sort_arrays()),
+      Array(xs) => {
+        xs.iter_mut().for_each(Self::sort_arrays);
+        if xs.iter().all(|x| matches!(x, Value(_))) {
+          xs.sort_by(|a, b| match (a, b) {
+            (Value(a), Value(b)) => compare_values(a, b),
+            _ => Ordering::Equal,
+          });
+        }
+      }
+    }
+  }
+}
+
+/// Finds the first line, counting from 1, where `a` and `b` differ, with the
+/// two versions of it. Splitting on `\n` only, unlike `lines`, keeps `\r`
+/// and a trailing newline visible, so different inputs always give a line.
+fn first_different_line<'s>(a: &'s str, b: &'s str) -> (usize, &'s str, &'s str) {
+  let (a, b) = (
+    a.split('\n').collect::<Vec<_>>(),
+    b.split('\n').collect::<Vec<_>>(),
+  );
+  let i = (0..a.len().max(b.len()))
+    .find(|&i| a.get(i) != b.get(i))
+    .unwrap_or(a.len().max(b.len()));
+  (
+    i + 1,
+    a.get(i).copied().unwrap_or(""),
+    b.get(i).copied().unwrap_or(""),
+  )
+}
+
+fn sort_entries_with_priority<T>(xs: &mut [(Cow<str>, T)], priority: &[&str]) {
+  let rank = |key: &str| {
+    priority
+      .iter()
+      .position(|x| *x == key)
+      .unwrap_or(priority.len())
+  };
+  xs.sort_by(|(a, _), (b, _)| {
+    let (a, b) = (unquote(a), unquote(b));
+    rank(a).cmp(&rank(b)).then_with(|| a.cmp(b))
+  });
+}
+
+/// Orders nodes by type: null, false, true, numbers, strings, objects, then
+/// arrays, with numbers and strings also ordered by value.
+fn compare_typed(a: &Node, b: &Node) -> Ordering {
+  fn rank(node: &Node) -> u8 {
+    match node {
+      Value(x) => match x.as_ref() {
+        "null" => 0,
+        "false" => 1,
+        "true" => 2,
+        x if x.starts_with('"') => 4,
+        _ => 3,
+      },
+      Object(_) => 5,
+      Array(_) => 6,
+    }
+  }
+
+  match (a, b) {
+    // Lenient input can have unquoted tokens other than numbers here.
+    (Value(x), Value(y)) if rank(a) == rank(b) && rank(a) == 3 => compare_values(x, y),
+    (Value(x), Value(y)) if rank(a) == rank(b) && rank(a) == 4 => compare_unescaped(x, y),
+    _ => rank(a).cmp(&rank(b)),
+  }
 }
 
 fn find_value<'a>(node: &'a Node, key: &str) -> Option<&'a str> {
   if let Object(xs) = node {
-    let qname = format!("\"{}\"", key);
     xs.iter().find_map(|(k, v)| match v {
-      Value(x) if *k == qname => Some(*x),
+      Value(x) if k.starts_with('"') && unquote(k) == key => Some(x.as_ref()),
       _ => None,
     })
   } else {
     None
   }
-}
+}
+
+/// Finds the value at a path of keys through nested objects.
+fn find_value_path<'a>(node: &'a Node, path: &[&str]) -> Option<&'a str> {
+  match path {
+    [] => None,
+    [key] => find_value(node, key),
+    [key, rest @ ..] => find_value_path(node.get(key)?, rest),
+  }
+}
+
+/// Compares two values numerically when both, unquoted, are finite numbers,
+/// so `"9"` sorts before `"10"`, and as text otherwise.
+fn compare_values(a: &str, b: &str) -> Ordering {
+  compare_numbers_first(number(a), number(b), a, b)
+}
+
+/// Orders numbers before everything else, so the order stays total: comparing
+/// a number with text as text would put `"10"` < `"1a"` < `"9"` < `"10"`.
+fn compare_numbers_first(x: Option<f64>, y: Option<f64>, a: &str, b: &str) -> Ordering {
+  match (x, y) {
+    (Some(x), Some(y)) => x.total_cmp(&y),
+    (Some(_), None) => Ordering::Less,
+    (None, Some(_)) => Ordering::Greater,
+    (None, None) => compare_unescaped(a, b),
+  }
+}
+
+/// Parses a value, unquoted, as a finite number.
+fn number(x: &str) -> Option<f64> {
+  unquote(x).parse::<f64>().ok().filter(|x| x.is_finite())
+}
+
+fn compare_versions(a: &str, b: &str) -> Ordering {
+  let version = |x: &str| {
+    let x = unquote(x);
+    Version::parse(x.strip_prefix('v').unwrap_or(x)).ok()
+  };
+  match (version(a), version(b)) {
+    (Some(x), Some(y)) => x.cmp(&y),
+    _ => compare_unescaped(a, b),
+  }
+}
+
+fn compare_unescaped(a: &str, b: &str) -> Ordering {
+  let (a, b) = (unquote(a), unquote(b));
+  if a.contains('\\') || b.contains('\\') {
+    unescape_value(a).cmp(&unescape_value(b))
+  } else {
+    a.cmp(b)
+  }
+}
+
+/// Decodes the escape sequences in the contents of a JSON string, leaving
+/// malformed sequences as they are.
+pub fn unescape_value(s: &str) -> String {
+  let mut buf = String::with_capacity(s.len());
+  let mut chars = s.chars();
+  while let Some(c) = chars.next() {
+    if c != '\\' {
+      buf.push(c);
+      continue;
+    }
+    match chars.next() {
+      Some('"') => buf.push('"'),
+      Some('\\') => buf.push('\\'),
+      Some('/') => buf.push('/'),
+      Some('b') => buf.push('\u{8}'),
+      Some('f') => buf.push('\u{c}'),
+      Some('n') => buf.push('\n'),
+      Some('r') => buf.push('\r'),
+      Some('t') => buf.push('\t'),
+      Some('u') => match read_unicode_escape(&mut chars) {
+        Some(x) => buf.push(x),
+        None => buf.push_str("\\u"),
+      },
+      Some(x) => {
+        buf.push('\\');
+        buf.push(x);
+      }
+      None => buf.push('\\'),
+    }
+  }
+  buf
+}
+
+fn read_unicode_escape(chars: &mut std::str::Chars) -> Option<char> {
+  let read_hex = |chars: &mut std::str::Chars| {
+    let hex = chars.as_str().get(..4)?;
+    if !hex.chars().all(|x| x.is_ascii_hexdigit()) {
+      return None;
+    }
+    let code = u32::from_str_radix(hex, 16).ok()?;
+    chars.nth(3);
+    Some(code)
+  };
+
+  let high = read_hex(chars)?;
+  if !(0xD800..0xDC00).contains(&high) {
+    return Some(char::from_u32(high).unwrap_or(char::REPLACEMENT_CHARACTER));
+  }
+
+  let mut rest = chars.clone();
+  if rest.next() == Some('\\') && rest.next() == Some('u') {
+    if let Some(low @ 0xDC00..=0xDFFF) = read_hex(&mut rest) {
+      *chars = rest;
+      return char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00));
+    }
+  }
+  Some(char::REPLACEMENT_CHARACTER)
+}
+
+/// Strips the quotes around a raw JSON string, leaving escapes as they are.
+pub fn unquote(s: &str) -> &str {
+  if s.len() > 1 && s.starts_with("\"") && s.ends_with("\"") {
+    &s[1..s.len() - 1]
+  } else {
+    s
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{
+    number, unquote, KeyMove, MissingKeyPosition, Node::*, SortError, SortKeyPipeline,
+    SortMismatch, SortMode, SortOptions,
+  };
+  use crate::parse::{parse, parse_lenient};
+  use std::cmp::Ordering;
+
+  #[test]
+  fn sort_by_name() {
+    let tests = vec![
+      (Value("1".into()), Value("1".into())),
+      (Object(vec![]), Object(vec![])),
+      (
+        Object(vec![("1".into(), Value("a".into()))]),
+        Object(vec![("1".into(), Value("a".into()))]),
+      ),
+      (
+        Object(vec![
+          ("1".into(), Value("a".into())),
+          ("2".into(), Value("b".into())),
+        ]),
+        Object(vec![
+          ("1".into(), Value("a".into())),
+          ("2".into(), Value("b".into())),
+        ]),
+      ),
+      (
+        Object(vec![
+          ("2".into(), Value("b".into())),
+          ("1".into(), Value("a".into())),
+        ]),
+        Object(vec![
+          ("1".into(), Value("a".into())),
+          ("2".into(), Value("b".into())),
+        ]),
+      ),
+      (
+        Object(vec![
+          ("\"a \"".into(), Value("x".into())),
+          ("\"a\"".into(), Value("x".into())),
+        ]),
+        Object(vec![
+          ("\"a\"".into(), Value("x".into())),
+          ("\"a \"".into(), Value("x".into())),
+        ]),
+      ),
+      (
+        Object(vec![
+          ("2".into(), Value("b".into())),
+          ("1".into(), Value("a".into())),
+          (
+            "3".into(),
+            Object(vec![
+              ("1".into(), Value("one".into())),
+              ("0".into(), Value("zero".into())),
+            ]),
+          ),
+        ]),
+        Object(vec![
+          ("1".into(), Value("a".into())),
+          ("2".into(), Value("b".into())),
+          (
+            "3".into(),
+            Object(vec![
+              ("0".into(), Value("zero".into())),
+              ("1".into(), Value("one".into())),
+            ]),
+          ),
+        ]),
+      ),
+      (
+        Object(vec![
+          ("2".into(), Value("b".into())),
+          ("1".into(), Value("a".into())),
+          (
+            "3".into(),
+            Array(vec![Object(vec![
+              ("1".into(), Value("one".into())),
+              ("0".into(), Value("zero".into())),
+            ])]),
+          ),
+        ]),
+        Object(vec![
+          ("1".into(), Value("a".into())),
+          ("2".into(), Value("b".into())),
+          (
+            "3".into(),
+            Array(vec![Object(vec![
+              ("0".into(), Value("zero".into())),
+              ("1".into(), Value("one".into())),
+            ])]),
+          ),
+        ]),
+      ),
+      (Array(vec![]), Array(vec![])),
+      (
+        Array(vec![Object(vec![
+          ("1".into(), Value("one".into())),
+          ("0".into(), Value("zero".into())),
+        ])]),
+        Array(vec![Object(vec![
+          ("0".into(), Value("zero".into())),
+          ("1".into(), Value("one".into())),
+        ])]),
+      ),
+      (
+        Array(vec![Object(vec![
+          ("1".into(), Value("one".into())),
+          (
+            "0".into(),
+            Array(vec![Object(vec![
+              ("y".into(), Value("yy".into())),
+              ("x".into(), Value("xx".into())),
+            ])]),
+          ),
+        ])]),
+        Array(vec![Object(vec![
+          (
+            "0".into(),
+            Array(vec![Object(vec![
+              ("x".into(), Value("xx".into())),
+              ("y".into(), Value("yy".into())),
+            ])]),
+          ),
+          ("1".into(), Value("one".into())),
+        ])]),
+      ),
+      (
+        Object(vec![
+          ("\"🗝️\"".into(), Value("1".into())),
+          ("\"🔑\"".into(), Value("2".into())),
+          ("\"z\"".into(), Value("3".into())),
+        ]),
+        Object(vec![
+          ("\"z\"".into(), Value("3".into())),
+          ("\"🔑\"".into(), Value("2".into())),
+          ("\"🗝️\"".into(), Value("1".into())),
+        ]),
+      ),
+      (
+        Object(vec![
+          ("\"🗝\"".into(), Value("1".into())),
+          ("\"🗝️\"".into(), Value("2".into())),
+        ]),
+        Object(vec![
+          ("\"🗝\"".into(), Value("1".into())),
+          ("\"🗝️\"".into(), Value("2".into())),
+        ]),
+      ),
+    ];
+
+    for (mut actual, expected) in tests {
+      actual.sort_by_name(&SortOptions::default());
+      assert_eq!(actual, expected);
+    }
+  }
+
+  #[test]
+  fn sort_by_name_unquoted_keys() {
+    let tests = vec![
+      ("{b: 1, a: 2}", r#"{"a":2,"b":1}"#),
+      (r#"{"c": 1, b: 2, "a": 3}"#, r#"{"a":3,"b":2,"c":1}"#),
+      (
+        "{b: {d: 1, c: 2}, a: [{f: 3, e: 4}]}",
+        r#"{"a":[{"e":4,"f":3}],"b":{"c":2,"d":1}}"#,
+      ),
+    ];
+    for (input, expected) in tests {
+      let mut actual = parse_lenient(input).unwrap();
+      actual.sort_by_name(&SortOptions::default());
+      assert_eq!(actual, parse(expected).unwrap(), "input: {}", input);
+    }
+  }
+
+  #[test]
+  fn verify_sort_by_name() {
+    let node = parse(r#"{"c":1,"b":{"e":[{"g":1,"f":2}],"d":3},"a":4}"#).unwrap();
+    assert_eq!(node.verify_sort_by_name(10), Ok(()));
+    assert_eq!(parse("1").unwrap().verify_sort_by_name(10), Ok(()));
+
+    let node = parse(r#"{"b":1,"a":1,"a":2}"#).unwrap();
+    assert_eq!(
+      node.verify_sort_by_name(10),
+      Err(SortMismatch {
+        run: 1,
+        line: 2,
+        expected: r#"  "a": 1,"#.to_owned(),
+        actual: r#"  "a": 2,"#.to_owned(),
+      })
+    );
+  }
+
+  #[test]
+  fn first_different_line() {
+    let tests = [
+      ("a\nb", "a\nc", (2, "b", "c")),
+      ("a\nb", "a\nb\n", (3, "", "")),
+      ("a\r\nb", "a\nb", (1, "a\r", "a")),
+      ("a", "a\nb", (2, "", "b")),
+      ("a", "a", (2, "", "")),
+    ];
+    for (a, b, expected) in tests {
+      assert_eq!(
+        super::first_different_line(a, b),
+        expected,
+        "{:?} vs {:?}",
+        a,
+        b
+      );
+    }
+  }
+
+  #[test]
+  fn sort_by_name_reverse() {
+    let reverse = SortOptions {
+      reverse: true,
+      ..Default::default()
+    };
+    let tests = [
+      ("1", "1"),
+      (r#"{"a":1,"c":2,"b":3}"#, r#"{"c":2,"b":3,"a":1}"#),
+      (r#"{"c":1,"b":2,"a":3}"#, r#"{"c":1,"b":2,"a":3}"#),
+      (
+        r#"[{"a":{"x":1,"y":2},"b":1}]"#,
+        r#"[{"b":1,"a":{"y":2,"x":1}}]"#,
+      ),
+    ];
+    for (input, expected) in tests {
+      let mut actual = parse(input).unwrap();
+      actual.sort_by_name(&reverse);
+      assert_eq!(actual, parse(expected).unwrap(), "input: {}", input);
+    }
+  }
+
+  #[test]
+  fn sort_by_name_case_insensitive() {
+    let options = SortOptions {
+      case_insensitive: true,
+      ..Default::default()
+    };
+    let tests = [
+      (r#"{"b":1,"B":2,"a":3}"#, r#"{"a":3,"B":2,"b":1}"#),
+      (r#"{"b":1,"A":2,"a":3}"#, r#"{"A":2,"a":3,"b":1}"#),
+      (
+        r#"[{"Zed":{"y":1,"X":2},"alpha":1}]"#,
+        r#"[{"alpha":1,"Zed":{"X":2,"y":1}}]"#,
+      ),
+    ];
+    for (input, expected) in tests {
+      let mut actual = parse(input).unwrap();
+      actual.sort_by_name(&options);
+      assert_eq!(actual, parse(expected).unwrap(), "input: {}", input);
+    }
+  }
+
+  #[test]
+  fn sort_by_name_with_diff() {
+    let mut node = parse(r#"{"c":1,"a":{"y":1,"x":2},"b":[{"e/f":1,"d":2}]}"#).unwrap();
+    let moves = node.sort_by_name_with_diff(&SortOptions::default());
+    assert_eq!(
+      node,
+      parse(r#"{"a":{"x":2,"y":1},"b":[{"d":2,"e/f":1}],"c":1}"#).unwrap()
+    );
+    let key_move = |key: &str, from, to| KeyMove {
+      key: key.to_owned(),
+      from,
+      to,
+    };
+    assert_eq!(
+      moves,
+      vec![
+        key_move("/a", 1, 0),
+        key_move("/b", 2, 1),
+        key_move("/c", 0, 2),
+        key_move("/a/x", 1, 0),
+        key_move("/a/y", 0, 1),
+        key_move("/b/0/d", 1, 0),
+        key_move("/b/0/e~1f", 0, 1),
+      ]
+    );
+    assert_eq!(
+      moves[0].to_string(),
+      r#"moved "/a" from position 1 to position 0"#
+    );
+    assert_eq!(node.sort_by_name_with_diff(&SortOptions::default()), vec![]);
+  }
+
+  #[test]
+  fn sort_by_name_single_key() {
+    let mut actual = parse(r#"{"a":{"c":1,"b":2}}"#).unwrap();
+    actual.sort_by_name(&SortOptions::default());
+    assert_eq!(actual, parse(r#"{"a":{"b":2,"c":1}}"#).unwrap());
+  }
+
+  #[test]
+  fn sort_by_name_with_priority() {
+    let tests = [
+      (vec![], r#"{"b":1,"a":2}"#, r#"{"a":2,"b":1}"#),
+      (
+        vec!["version", "name"],
+        r#"{"b":1,"name":2,"a":{"name":3,"c":4,"version":5},"version":6}"#,
+        r#"{"version":6,"name":2,"a":{"version":5,"name":3,"c":4},"b":1}"#,
+      ),
+    ];
+    for (priority, input, expected) in tests {
+      let mut actual = parse(input).unwrap();
+      actual.sort_by_name_with_priority(&priority);
+      assert_eq!(actual, parse(expected).unwrap(), "input: {}", input);
+    }
+
+    let mut actual = parse(r#"{"a":1,"id":2,"B":3,"name":4}"#).unwrap();
+    actual.sort_by_name(&SortOptions {
+      reverse: true,
+      case_insensitive: true,
+      priority: vec!["name".into(), "id".into()],
+      ..Default::default()
+    });
+    assert_eq!(actual, parse(r#"{"name":4,"id":2,"B":3,"a":1}"#).unwrap());
+  }
+
+  #[test]
+  fn sort_by_name_with_order_hint() {
+    let tests = [
+      ("1", "1"),
+      (r#"{"b":1,"a":2}"#, r#"{"a":2,"b":1}"#),
+      (
+        r#"{"a":1,"c":2,"__order":["c","x"],"b":3}"#,
+        r#"{"c":2,"a":1,"b":3}"#,
+      ),
+      (
+        r#"[{"z":{"__order":["y"],"x":1,"y":2},"__order":"z","a":0,"__order":[]}]"#,
+        r#"[{"a":0,"z":{"y":2,"x":1}}]"#,
+      ),
+      (
+        r#"{"b":1,"a":2,"__order":[1,null,"b"]}"#,
+        r#"{"b":1,"a":2}"#,
+      ),
+    ];
+    for (input, expected) in tests {
+      let mut actual = parse(input).unwrap();
+      actual.sort_by_name_with_order_hint("__order");
+      assert_eq!(actual, parse(expected).unwrap(), "input: {}", input);
+    }
+  }
+
+  #[test]
+  fn sort_by_name_alternating() {
+    let tests = vec![
+      (0, "1", "1"),
+      (0, r#"{"b":1,"a":2}"#, r#"{"a":2,"b":1}"#),
+      (1, r#"{"a":1,"b":2}"#, r#"{"b":2,"a":1}"#),
+      (
+        0,
+        r#"{"b":{"x":{"q":1,"p":2},"y":3},"a":{"x":1,"y":2}}"#,
+        r#"{"a":{"y":2,"x":1},"b":{"y":3,"x":{"p":2,"q":1}}}"#,
+      ),
+      (
+        0,
+        r#"[{"a":{"b":1,"c":{"e":1,"d":2}}}]"#,
+        r#"[{"a":{"b":1,"c":{"e":1,"d":2}}}]"#,
+      ),
+      (
+        0,
+        r#"[{"a":{"c":{"d":2,"e":1},"b":1}}]"#,
+        r#"[{"a":{"b":1,"c":{"e":1,"d":2}}}]"#,
+      ),
+    ];
+
+    for (depth, input, expected) in tests {
+      let mut actual = parse(input).unwrap();
+      actual.sort_by_name_alternating(depth);
+      assert_eq!(actual, parse(expected).unwrap(), "\n input: `{}`\n", input);
+    }
+  }
+
+  #[test]
+  fn normalize_literal() {
+    let tests = [
+      ("null", "null"),
+      ("TRUE", "true"),
+      ("False", "false"),
+      ("Nullable", "Nullable"),
+      ("\"TRUE\"", "\"TRUE\""),
+    ];
+    for (input, expected) in tests {
+      assert_eq!(
+        super::normalize_literal(input),
+        expected,
+        "input: {}",
+        input
+      );
+    }
+  }
+
+  #[test]
+  fn sort_by_name_with_comparator() {
+    let by_length = |a: &str, b: &str| a.len().cmp(&b.len()).then_with(|| b.cmp(a));
+    let tests = [
+      ("1", "1"),
+      (r#"{"ccc":1,"a":2,"bb":3}"#, r#"{"a":2,"bb":3,"ccc":1}"#),
+      (
+        r#"[{"b":{"yy":1,"x":2},"a":3}]"#,
+        r#"[{"b":{"x":2,"yy":1},"a":3}]"#,
+      ),
+    ];
+
+    for (input, expected) in tests {
+      let mut node = parse(input).unwrap();
+      node.sort_by_name_with_comparator(&by_length);
+      assert_eq!(node.to_compact_string(), expected, "input: {}", input);
+    }
+  }
+
+  #[test]
+  fn sort_by_name_piped() {
+    let lowercase = || {
+      SortKeyPipeline::new()
+        .then(|x| unquote(x).to_owned())
+        .then(str::to_lowercase)
+    };
+    let strip_prefix = || {
+      SortKeyPipeline::new()
+        .then(|x| unquote(x).trim().to_owned())
+        .then(|x| x.strip_prefix("x-").unwrap_or(x).to_owned())
+    };
+    let tests = [
+      (SortKeyPipeline::new(), Value("1".into()), Value("1".into())),
+      (
+        SortKeyPipeline::new(),
+        Object(vec![
+          ("\"b\"".into(), Value("1".into())),
+          ("\"a\"".into(), Value("2".into())),
+        ]),
+        Object(vec![
+          ("\"a\"".into(), Value("2".into())),
+          ("\"b\"".into(), Value("1".into())),
+        ]),
+      ),
+      (
+        lowercase(),
+        Object(vec![
+          ("\"b\"".into(), Value("1".into())),
+          ("\"C\"".into(), Value("2".into())),
+          ("\"A\"".into(), Value("3".into())),
+        ]),
+        Object(vec![
+          ("\"A\"".into(), Value("3".into())),
+          ("\"b\"".into(), Value("1".into())),
+          ("\"C\"".into(), Value("2".into())),
+        ]),
+      ),
+      (
+        lowercase(),
+        Array(vec![Object(vec![
+          ("\"B\"".into(), Value("1".into())),
+          ("\"a\"".into(), Value("2".into())),
+        ])]),
+        Array(vec![Object(vec![
+          ("\"a\"".into(), Value("2".into())),
+          ("\"B\"".into(), Value("1".into())),
+        ])]),
+      ),
+      (
+        strip_prefix(),
+        Object(vec![
+          ("\"x-c\"".into(), Value("1".into())),
+          ("\" b\"".into(), Value("2".into())),
+          ("\"a\"".into(), Value("3".into())),
+        ]),
+        Object(vec![
+          ("\"a\"".into(), Value("3".into())),
+          ("\" b\"".into(), Value("2".into())),
+          ("\"x-c\"".into(), Value("1".into())),
+        ]),
+      ),
+    ];
+
+    for (pipeline, mut actual, expected) in tests {
+      actual.sort_by_name_piped(&pipeline);
+      assert_eq!(actual, expected);
+    }
+  }
 
-fn unquote(s: &str) -> &str {
-  if s.len() > 1 && s.starts_with("\"") && s.ends_with("\"") {
-    &s[1..s.len() - 1]
-  } else {
-    s
+  #[test]
+  fn sort_by_name_arrays_as_block() {
+    let tests = [
+      ("[]", "[]"),
+      (r#"{"b":1,"a":[]}"#, r#"{"a":[],"b":1}"#),
+      (
+        r#"{"rules":[{"name":"z","id":1}],"b":1,"j":2}"#,
+        r#"{"b":1,"rules":[{"id":1,"name":"z"}],"j":2}"#,
+      ),
+      (r#"{"z":[1],"a":[{"y":1}]}"#, r#"{"a":[{"y":1}],"z":[1]}"#),
+      (r#"{"x":1,"a":[{"y":1}]}"#, r#"{"x":1,"a":[{"y":1}]}"#),
+    ];
+    for (input, expected) in tests {
+      let mut actual = parse(input).unwrap();
+      actual.sort_by_name_arrays_as_block();
+      assert_eq!(actual, parse(expected).unwrap(), "input: {}", input);
+    }
   }
-}
 
-#[cfg(test)]
-mod tests {
-  use super::Node::*;
+  #[cfg(feature = "xxhash")]
+  #[test]
+  fn sort_by_name_hash_order() {
+    use xxhash_rust::xxh3::xxh3_64;
+
+    let mut node = parse(r#"{"a":1,"b":{"y":1,"x":2},"c":3,"d":4}"#).unwrap();
+    node.sort_by_name_hash_order();
+    let Object(xs) = &node else { panic!() };
+    let keys = xs.iter().map(|(k, _)| unquote(k)).collect::<Vec<_>>();
+    let mut expected = vec!["a", "b", "c", "d"];
+    expected.sort_by_key(|x| xxh3_64(x.as_bytes()));
+    assert_eq!(keys, expected);
 
+    let mut again = node.clone();
+    again.sort_by_name_hash_order();
+    assert_eq!(again, node);
+  }
+
+  #[cfg(feature = "icu")]
   #[test]
-  fn sort_by_name() {
+  fn sort_by_name_with_locale() {
+    use super::LocaleError;
+
     let tests = vec![
-      (Value("1"), Value("1")),
-      (Object(vec![]), Object(vec![])),
       (
-        Object(vec![("1", Value("a"))]),
-        Object(vec![("1", Value("a"))]),
+        "de-DE",
+        r#"{"Zebra":1,"Öl":2,"ob":3,"Äpfel":4,"Apfel":5,"Ofen":{"ü":1,"u":2,"v":3}}"#,
+        Ok(r#"{"Apfel":5,"Äpfel":4,"ob":3,"Ofen":{"u":2,"ü":1,"v":3},"Öl":2,"Zebra":1}"#),
+      ),
+      (
+        "zh-CN",
+        r#"{"赵":1,"张":2,"王":3,"李":4}"#,
+        Ok(r#"{"李":4,"王":3,"张":2,"赵":1}"#),
+      ),
+      (
+        "zh-CN-u-co-stroke",
+        r#"{"赵":1,"张":2,"王":3,"李":4}"#,
+        Ok(r#"{"王":3,"张":2,"李":4,"赵":1}"#),
       ),
       (
-        Object(vec![("1", Value("a")), ("2", Value("b"))]),
-        Object(vec![("1", Value("a")), ("2", Value("b"))]),
+        "not a locale",
+        r#"{"b":1,"a":2}"#,
+        Err(LocaleError::Invalid("not a locale".to_owned())),
       ),
+    ];
+    for (locale, input, expected) in tests {
+      let mut actual = parse(input).unwrap();
+      let result = actual.sort_by_name_with_locale(locale);
+      let expected = expected.map(|x| parse(x).unwrap());
+      assert_eq!(result.map(|_| actual), expected, "locale: {}", locale);
+    }
+  }
+
+  #[cfg(feature = "random")]
+  #[test]
+  fn shuffle_keys() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let input = r#"{"a":1,"b":2,"c":3,"d":4,"e":[{"f":5,"g":6,"h":7,"i":8}]}"#;
+    let shuffle = |seed| {
+      let mut node = parse(input).unwrap();
+      node.shuffle_keys(&mut StdRng::seed_from_u64(seed));
+      node
+    };
+
+    assert_eq!(shuffle(1), shuffle(1));
+    assert!((0..10).any(|seed| shuffle(seed) != parse(input).unwrap()));
+    for seed in 0..10 {
+      let mut actual = shuffle(seed);
+      actual.sort_by_name(&SortOptions::default());
+      assert_eq!(actual, parse(input).unwrap());
+    }
+  }
+
+  #[test]
+  fn sort_by_schema() {
+    let schema = r#"{
+      "properties": {
+        "name": {},
+        "id": {},
+        "address": {"properties": {"street": {}, "city": {}}},
+        "tags": {"items": {"properties": {"value": {}, "key": {}}}}
+      }
+    }"#;
+    let tests = vec![
+      ("1", "1"),
+      (r#"{"id":1,"name":2}"#, r#"{"name":2,"id":1}"#),
+      (r#"{"z":1,"id":2,"b":3}"#, r#"{"id":2,"b":3,"z":1}"#),
       (
-        Object(vec![("2", Value("b")), ("1", Value("a"))]),
-        Object(vec![("1", Value("a")), ("2", Value("b"))]),
+        r#"{"address":{"zip":1,"city":2,"street":3},"name":4}"#,
+        r#"{"name":4,"address":{"street":3,"city":2,"zip":1}}"#,
       ),
       (
-        Object(vec![("\"a \"", Value("x")), ("\"a\"", Value("x"))]),
-        Object(vec![("\"a\"", Value("x")), ("\"a \"", Value("x"))]),
+        r#"{"tags":[{"key":1,"value":2}],"other":{"y":1,"x":2}}"#,
+        r#"{"tags":[{"value":2,"key":1}],"other":{"x":2,"y":1}}"#,
       ),
+      (r#"[{"id":1,"name":2}]"#, r#"[{"id":1,"name":2}]"#),
+    ];
+
+    let schema = parse(schema).unwrap();
+    for (input, expected) in tests {
+      let mut actual = parse(input).unwrap();
+      actual.sort_by_schema(&schema);
+      assert_eq!(actual, parse(expected).unwrap(), "\n input: `{}`\n", input);
+    }
+  }
+
+  #[test]
+  fn is_sorted_by_name() {
+    let tests = [
+      (Value("1".into()), true),
+      (Object(vec![]), true),
+      (Array(vec![]), true),
+      (Object(vec![("\"a\"".into(), Value("1".into()))]), true),
       (
         Object(vec![
-          ("2", Value("b")),
-          ("1", Value("a")),
-          ("3", Object(vec![("1", Value("one")), ("0", Value("zero"))])),
+          ("\"a\"".into(), Value("1".into())),
+          ("\"b\"".into(), Value("1".into())),
         ]),
+        true,
+      ),
+      (
         Object(vec![
-          ("1", Value("a")),
-          ("2", Value("b")),
-          ("3", Object(vec![("0", Value("zero")), ("1", Value("one"))])),
+          ("\"b\"".into(), Value("1".into())),
+          ("\"a\"".into(), Value("1".into())),
         ]),
+        false,
       ),
       (
         Object(vec![
-          ("2", Value("b")),
-          ("1", Value("a")),
-          (
-            "3",
-            Array(vec![Object(vec![
-              ("1", Value("one")),
-              ("0", Value("zero")),
-            ])]),
-          ),
+          ("\"a\"".into(), Value("1".into())),
+          ("\"a\"".into(), Value("0".into())),
         ]),
-        Object(vec![
-          ("1", Value("a")),
-          ("2", Value("b")),
-          (
-            "3",
-            Array(vec![Object(vec![
-              ("0", Value("zero")),
-              ("1", Value("one")),
-            ])]),
-          ),
+        true,
+      ),
+      (
+        Object(vec![(
+          "\"a\"".into(),
+          Object(vec![
+            ("\"y\"".into(), Value("1".into())),
+            ("\"x\"".into(), Value("1".into())),
+          ]),
+        )]),
+        false,
+      ),
+      (
+        Array(vec![
+          Object(vec![("\"a\"".into(), Value("1".into()))]),
+          Object(vec![
+            ("\"c\"".into(), Value("1".into())),
+            ("\"b\"".into(), Value("1".into())),
+          ]),
         ]),
+        false,
       ),
-      (Array(vec![]), Array(vec![])),
+    ];
+
+    for (node, expected) in tests {
+      assert_eq!(node.is_sorted_by_name(), expected, "{:?}", node);
+    }
+  }
+
+  #[test]
+  fn assert_sorted_by_name() {
+    let error = |path: &str, before: &str, after: &str| SortError {
+      path: path.to_owned(),
+      key_before: before.to_owned(),
+      key_after: after.to_owned(),
+    };
+    let tests = [
+      ("1", Ok(())),
+      (r#"{"a":1,"b":{"c":1,"d":2}}"#, Ok(())),
+      (r#"{"b":1,"a":2}"#, Err(error("", "\"b\"", "\"a\""))),
       (
-        Array(vec![Object(vec![
-          ("1", Value("one")),
-          ("0", Value("zero")),
-        ])]),
-        Array(vec![Object(vec![
-          ("0", Value("zero")),
-          ("1", Value("one")),
-        ])]),
+        r#"{"a":[{"x":1},{"z":1,"y":2}],"b":{"e":1,"d":1}}"#,
+        Err(error("/a/1", "\"z\"", "\"y\"")),
       ),
       (
-        Array(vec![Object(vec![
-          ("1", Value("one")),
-          (
-            "0",
-            Array(vec![Object(vec![("y", Value("yy")), ("x", Value("xx"))])]),
-          ),
-        ])]),
-        Array(vec![Object(vec![
-          (
-            "0",
-            Array(vec![Object(vec![("x", Value("xx")), ("y", Value("yy"))])]),
-          ),
-          ("1", Value("one")),
-        ])]),
+        r#"{"a/b":{"d":1,"c":1}}"#,
+        Err(error("/a~1b", "\"d\"", "\"c\"")),
       ),
     ];
 
-    for (mut actual, expected) in tests {
-      actual.sort_by_name();
-      assert_eq!(actual, expected);
+    for (input, expected) in tests {
+      assert_eq!(
+        parse(input).unwrap().assert_sorted_by_name(),
+        expected,
+        "input: {}",
+        input
+      );
+    }
+  }
+
+  #[test]
+  fn assert_sorted_by_value() {
+    let tests = [
+      (r#"[{"x":1},{"x":2},{"y":0}]"#, Ok(())),
+      (r#"{"a":[{"x":"a"},{"x":"b"}]}"#, Ok(())),
+      (
+        r#"{"a":[{"x":1},{"x":10},{"x":9}]}"#,
+        Err(SortError {
+          path: "/a".to_owned(),
+          key_before: "10".to_owned(),
+          key_after: "9".to_owned(),
+        }),
+      ),
+    ];
+
+    for (input, expected) in tests {
+      assert_eq!(
+        parse(input).unwrap().assert_sorted_by_value("x"),
+        expected,
+        "input: {}",
+        input
+      );
+    }
+  }
+
+  #[test]
+  fn emit_key_order() {
+    let tests = [
+      ("1", vec![]),
+      ("{}", vec![vec![]]),
+      (
+        r#"{"b":{"d":1,"c":2},"a":1}"#,
+        vec![vec![r#""b""#, r#""a""#]],
+      ),
+      (
+        r#"[{"b":1,"a":2},1,{"c":3}]"#,
+        vec![vec![r#""b""#, r#""a""#], vec![r#""c""#]],
+      ),
+    ];
+    for (input, expected) in tests {
+      assert_eq!(
+        parse(input).unwrap().emit_key_order(),
+        expected,
+        "input: {}",
+        input
+      );
+    }
+
+    let node = Object(vec![
+      ("b".into(), Value("1".into())),
+      ("a\"".into(), Value("2".into())),
+      (r#""c\"""#.into(), Value("3".into())),
+    ]);
+    assert_eq!(
+      node.emit_key_order(),
+      vec![vec![r#""b""#, r#""a\"""#, r#""c\"""#]]
+    );
+    let order = Array(
+      node
+        .emit_key_order()
+        .into_iter()
+        .map(|keys| Array(keys.into_iter().map(Value).collect()))
+        .collect(),
+    );
+    assert_eq!(parse(&order.to_string()).unwrap(), order);
+  }
+
+  #[test]
+  fn sort_by_value_and_name() {
+    let mut actual =
+      parse(r#"{"b":[{"y":1,"k":2},{"k":1}],"a":{"d":[{"k":"b"},{"k":"a"}],"c":1}}"#).unwrap();
+    actual.sort_by_value_and_name("k");
+    assert_eq!(
+      actual,
+      parse(r#"{"a":{"c":1,"d":[{"k":"a"},{"k":"b"}]},"b":[{"k":1},{"k":2,"y":1}]}"#).unwrap()
+    );
+  }
+
+  #[test]
+  fn sort_by_name_then_value() {
+    let tests = [
+      ("[]", "[]"),
+      (
+        r#"[{"id":2,"a":1},{"b":1},{"a":2,"id":1},{"a":3}]"#,
+        r#"[{"a":3},{"a":2,"id":1},{"a":1,"id":2},{"b":1}]"#,
+      ),
+      (
+        r#"{"y":[{"id":"b"},{"id":"a"}],"x":[1,{"id":0}]}"#,
+        r#"{"x":[1,{"id":0}],"y":[{"id":"a"},{"id":"b"}]}"#,
+      ),
+    ];
+    for (input, expected) in tests {
+      let mut actual = parse(input).unwrap();
+      actual.sort_by_name_then_value("id");
+      assert_eq!(actual, parse(expected).unwrap(), "input: {}", input);
+    }
+  }
+
+  #[test]
+  fn sort_by_type_then_value() {
+    let tests = [
+      ("[]", "[]"),
+      (
+        r#"[{"k":[]},{"k":"b"},{"k":{}},{"k":10},{"k":"a"},{"k":true},{"k":2},{"k":false},{"k":null}]"#,
+        r#"[{"k":null},{"k":false},{"k":true},{"k":2},{"k":10},{"k":"a"},{"k":"b"},{"k":{}},{"k":[]}]"#,
+      ),
+      (
+        r#"[{"x":1},{"k":"a"},{"k":null}]"#,
+        r#"[{"k":null},{"k":"a"},{"x":1}]"#,
+      ),
+      (
+        r#"{"a":[{"k":1},{"k":null}]}"#,
+        r#"{"a":[{"k":null},{"k":1}]}"#,
+      ),
+      (
+        r#"[{"k":b},{"k":10},{"k":a},{"k":9},{"k":"c"}]"#,
+        r#"[{"k":9},{"k":10},{"k":a},{"k":b},{"k":"c"}]"#,
+      ),
+    ];
+    for (input, expected) in tests {
+      let mut actual = parse(input).unwrap();
+      actual.sort_by_type_then_value("k");
+      assert_eq!(actual, parse(expected).unwrap(), "input: {}", input);
     }
   }
 
   #[test]
   fn sort_by_value() {
     let tests = [
-      ("", Value("1"), Value("1")),
+      ("", Value("1".into()), Value("1".into())),
       ("", Object(vec![]), Object(vec![])),
       ("", Array(vec![]), Array(vec![])),
       (
         "name",
         Array(vec![
-          Object(vec![("\"name\"", Value("1"))]),
-          Object(vec![("\"name\"", Value("2"))]),
+          Object(vec![("\"name\"".into(), Value("1".into()))]),
+          Object(vec![("\"name\"".into(), Value("2".into()))]),
         ]),
         Array(vec![
-          Object(vec![("\"name\"", Value("1"))]),
-          Object(vec![("\"name\"", Value("2"))]),
+          Object(vec![("\"name\"".into(), Value("1".into()))]),
+          Object(vec![("\"name\"".into(), Value("2".into()))]),
         ]),
       ),
       (
         "name",
         Array(vec![
-          Object(vec![("\"name\"", Value("2"))]),
-          Object(vec![("\"name\"", Value("1"))]),
+          Object(vec![("\"name\"".into(), Value("2".into()))]),
+          Object(vec![("\"name\"".into(), Value("1".into()))]),
         ]),
         Array(vec![
-          Object(vec![("\"name\"", Value("1"))]),
-          Object(vec![("\"name\"", Value("2"))]),
+          Object(vec![("\"name\"".into(), Value("1".into()))]),
+          Object(vec![("\"name\"".into(), Value("2".into()))]),
         ]),
       ),
       (
         "name",
         Object(vec![(
-          "\"name\"",
+          "\"name\"".into(),
           Array(vec![
-            Object(vec![("\"name\"", Value("2"))]),
-            Object(vec![("\"name\"", Value("1"))]),
+            Object(vec![("\"name\"".into(), Value("2".into()))]),
+            Object(vec![("\"name\"".into(), Value("1".into()))]),
           ]),
         )]),
         Object(vec![(
-          "\"name\"",
+          "\"name\"".into(),
           Array(vec![
-            Object(vec![("\"name\"", Value("1"))]),
-            Object(vec![("\"name\"", Value("2"))]),
+            Object(vec![("\"name\"".into(), Value("1".into()))]),
+            Object(vec![("\"name\"".into(), Value("2".into()))]),
           ]),
         )]),
       ),
       (
         "a",
         Array(vec![
-          Object(vec![("\"a\"", Value("1"))]),
-          Object(vec![("\"a\"", Value("2"))]),
-          Object(vec![("\"a\"", Value("0"))]),
+          Object(vec![("\"a\"".into(), Value("1".into()))]),
+          Object(vec![("\"a\"".into(), Value("2".into()))]),
+          Object(vec![("\"a\"".into(), Value("0".into()))]),
         ]),
         Array(vec![
-          Object(vec![("\"a\"", Value("0"))]),
-          Object(vec![("\"a\"", Value("1"))]),
-          Object(vec![("\"a\"", Value("2"))]),
+          Object(vec![("\"a\"".into(), Value("0".into()))]),
+          Object(vec![("\"a\"".into(), Value("1".into()))]),
+          Object(vec![("\"a\"".into(), Value("2".into()))]),
         ]),
       ),
       (
         "a",
         Array(vec![
-          Object(vec![("\"a\"", Value("\"cmd+h c\""))]),
-          Object(vec![("\"a\"", Value("\"cmd+h\""))]),
+          Object(vec![("\"a\"".into(), Value("\"cmd+h c\"".into()))]),
+          Object(vec![("\"a\"".into(), Value("\"cmd+h\"".into()))]),
         ]),
         Array(vec![
-          Object(vec![("\"a\"", Value("\"cmd+h\""))]),
-          Object(vec![("\"a\"", Value("\"cmd+h c\""))]),
+          Object(vec![("\"a\"".into(), Value("\"cmd+h\"".into()))]),
+          Object(vec![("\"a\"".into(), Value("\"cmd+h c\"".into()))]),
         ]),
       ),
       (
         "a",
         Array(vec![
-          Object(vec![("\"a\"", Value("1"))]),
-          Object(vec![("\"a\"", Value("0"))]),
+          Object(vec![("\"a\"".into(), Value("1".into()))]),
+          Object(vec![("\"a\"".into(), Value("0".into()))]),
           Object(vec![(
-            "\"b\"",
+            "\"b\"".into(),
             Array(vec![
-              Object(vec![("\"a\"", Value("2"))]),
-              Object(vec![("\"a\"", Value("1"))]),
+              Object(vec![("\"a\"".into(), Value("2".into()))]),
+              Object(vec![("\"a\"".into(), Value("1".into()))]),
             ]),
           )]),
         ]),
         Array(vec![
-          Object(vec![("\"a\"", Value("0"))]),
-          Object(vec![("\"a\"", Value("1"))]),
+          Object(vec![("\"a\"".into(), Value("0".into()))]),
+          Object(vec![("\"a\"".into(), Value("1".into()))]),
           Object(vec![(
-            "\"b\"",
+            "\"b\"".into(),
             Array(vec![
-              Object(vec![("\"a\"", Value("1"))]),
-              Object(vec![("\"a\"", Value("2"))]),
+              Object(vec![("\"a\"".into(), Value("1".into()))]),
+              Object(vec![("\"a\"".into(), Value("2".into()))]),
             ]),
           )]),
         ]),
       ),
+      (
+        "inner_key",
+        Array(vec![Object(vec![(
+          "\"outer_key\"".into(),
+          Array(vec![
+            Object(vec![("\"inner_key\"".into(), Value("3".into()))]),
+            Object(vec![("\"inner_key\"".into(), Value("1".into()))]),
+          ]),
+        )])]),
+        Array(vec![Object(vec![(
+          "\"outer_key\"".into(),
+          Array(vec![
+            Object(vec![("\"inner_key\"".into(), Value("1".into()))]),
+            Object(vec![("\"inner_key\"".into(), Value("3".into()))]),
+          ]),
+        )])]),
+      ),
     ];
 
     for (key, mut actual, expected) in tests {
-      actual.sort_by_value(key);
+      actual.sort_by_value(key, &SortOptions::default());
+      assert_eq!(actual, expected);
+    }
+  }
+
+  #[test]
+  fn sort_by_value_reverse() {
+    let reverse = SortOptions {
+      reverse: true,
+      ..Default::default()
+    };
+    let tests = [
+      ("[]", "[]"),
+      (
+        r#"[{"k":"b"},{"k":"c"},{"x":1},{"k":"a"}]"#,
+        r#"[{"k":"c"},{"k":"b"},{"x":1},{"k":"a"}]"#,
+      ),
+      (r#"{"a":[{"k":1},{"k":2}]}"#, r#"{"a":[{"k":2},{"k":1}]}"#),
+    ];
+    for (input, expected) in tests {
+      let mut actual = parse(input).unwrap();
+      actual.sort_by_value("k", &reverse);
+      assert_eq!(actual, parse(expected).unwrap(), "input: {}", input);
+    }
+  }
+
+  #[test]
+  fn compare_values() {
+    let tests = [
+      ("2", "10", Ordering::Less),
+      ("\"10\"", "\"9\"", Ordering::Greater),
+      ("\"1.5\"", "2", Ordering::Less),
+      ("-1", "\"-10\"", Ordering::Greater),
+      ("1e2", "\"99\"", Ordering::Greater),
+      ("\"1.0\"", "1", Ordering::Equal),
+      ("\"10\"", "\"a\"", Ordering::Less),
+      ("\"b\"", "\"a\"", Ordering::Greater),
+      ("\"inf\"", "\"1\"", Ordering::Greater),
+      ("\"9\"", "\"1a\"", Ordering::Less),
+      ("\"1a\"", "\"10\"", Ordering::Greater),
+    ];
+    for (a, b, expected) in tests {
+      assert_eq!(super::compare_values(a, b), expected, "{} vs {}", a, b);
+    }
+  }
+
+  #[test]
+  fn sort_by_value_numeric() {
+    let tests = [
+      (
+        r#"[{"k":"10"},{"k":"9"},{"k":"2"}]"#,
+        r#"[{"k":"2"},{"k":"9"},{"k":"10"}]"#,
+      ),
+      (
+        r#"[{"k":10},{"k":"b"},{"k":9.5},{"k":"a"},{"k":"-3"}]"#,
+        r#"[{"k":"-3"},{"k":9.5},{"k":10},{"k":"a"},{"k":"b"}]"#,
+      ),
+    ];
+    for (input, expected) in tests {
+      let mut actual = parse(input).unwrap();
+      actual.sort_by_value("k", &SortOptions::default());
+      assert_eq!(actual, parse(expected).unwrap(), "input: {}", input);
+    }
+
+    // Large enough for the sort to detect an inconsistent comparator.
+    let values = (0..300)
+      .map(|i| match i % 3 {
+        0 => format!("{}", (i * 7919) % 100),
+        1 => format!("\"{}\"", (i * 104729) % 100),
+        _ => format!("\"{}{}\"", i % 10, ["a", "b"][i % 2]),
+      })
+      .collect::<Vec<_>>();
+    let input = format!(
+      "[{}]",
+      values
+        .iter()
+        .map(|x| format!(r#"{{"k":{}}}"#, x))
+        .collect::<Vec<_>>()
+        .join(",")
+    );
+    let mut actual = parse(&input).unwrap();
+    actual.sort_by_value("k", &SortOptions::default());
+    let Array(xs) = actual else { panic!() };
+    let sorted = xs.iter().map(|x| x.get("k").unwrap()).collect::<Vec<_>>();
+    let split = sorted.iter().position(|x| number(&x.to_string()).is_none());
+    let (numbers, text) = sorted.split_at(split.unwrap());
+    assert!(numbers
+      .windows(2)
+      .all(|w| number(&w[0].to_string()) <= number(&w[1].to_string())));
+    assert!(text
+      .windows(2)
+      .all(|w| w[0].to_string() <= w[1].to_string()));
+    assert!(text.iter().all(|x| number(&x.to_string()).is_none()));
+  }
+
+  #[test]
+  fn find_value_path() {
+    let node = parse(r#"{"a":{"b":{"c":"x"},"d":1},"e":[1]}"#).unwrap();
+    let tests = [
+      (vec![], None),
+      (vec!["a"], None),
+      (vec!["e"], None),
+      (vec!["a", "d"], Some("1")),
+      (vec!["a", "b", "c"], Some(r#""x""#)),
+      (vec!["a", "x", "c"], None),
+      (vec!["a", "d", "c"], None),
+    ];
+    for (path, expected) in tests {
+      assert_eq!(
+        super::find_value_path(&node, &path),
+        expected,
+        "path: {:?}",
+        path
+      );
+    }
+  }
+
+  #[test]
+  fn sort_by_values() {
+    let tests = [
+      (
+        vec!["name", "age"],
+        r#"[{"name":"b","age":1},{"name":"a","age":30},{"name":"a","age":4}]"#,
+        r#"[{"name":"a","age":4},{"name":"a","age":30},{"name":"b","age":1}]"#,
+      ),
+      (
+        vec!["age", "name"],
+        r#"[{"name":"b","age":1},{"name":"a","age":1},{"name":"a","age":0}]"#,
+        r#"[{"name":"a","age":0},{"name":"a","age":1},{"name":"b","age":1}]"#,
+      ),
+      (
+        vec!["name", "age"],
+        r#"[{"name":"b"},{"name":"a","age":2},{"name":"a","age":1}]"#,
+        r#"[{"name":"a","age":1},{"name":"a","age":2},{"name":"b"}]"#,
+      ),
+      (
+        vec!["address.city"],
+        r#"[{"address":{"city":"b"}},{"address":{"city":"a"}}]"#,
+        r#"[{"address":{"city":"a"}},{"address":{"city":"b"}}]"#,
+      ),
+      (
+        vec!["a.b.c", "d"],
+        r#"[{"a":{"b":{"c":2}},"d":1},{"a":{"b":{"c":1}},"d":2},{"a":{"b":{"c":1}},"d":1}]"#,
+        r#"[{"a":{"b":{"c":1}},"d":1},{"a":{"b":{"c":1}},"d":2},{"a":{"b":{"c":2}},"d":1}]"#,
+      ),
+    ];
+    for (names, input, expected) in tests {
+      let paths = names
+        .iter()
+        .map(|x| x.split('.').collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+      let paths = paths.iter().map(Vec::as_slice).collect::<Vec<_>>();
+      let mut actual = parse(input).unwrap();
+      actual.sort_by_values(&paths, &SortOptions::default());
+      assert_eq!(actual, parse(expected).unwrap(), "input: {}", input);
+    }
+  }
+
+  #[test]
+  fn sort_by_value_missing_key() {
+    let input = r#"[{"x":1},{"k":2},{"y":1},{"k":1},{"x":2}]"#;
+    let tests = [
+      (
+        MissingKeyPosition::First,
+        false,
+        r#"[{"x":1},{"y":1},{"x":2},{"k":1},{"k":2}]"#,
+      ),
+      (
+        MissingKeyPosition::Last,
+        false,
+        r#"[{"k":1},{"k":2},{"x":1},{"y":1},{"x":2}]"#,
+      ),
+      (
+        MissingKeyPosition::Last,
+        true,
+        r#"[{"k":2},{"k":1},{"x":1},{"y":1},{"x":2}]"#,
+      ),
+    ];
+    for (missing_key, reverse, expected) in tests {
+      let mut actual = parse(input).unwrap();
+      actual.sort_by_value(
+        "k",
+        &SortOptions {
+          missing_key,
+          reverse,
+          ..Default::default()
+        },
+      );
+      assert_eq!(actual, parse(expected).unwrap(), "{:?}", missing_key);
+    }
+  }
+
+  #[test]
+  fn sort_by_value_version_string() {
+    let options = SortOptions {
+      mode: SortMode::VersionString,
+      ..Default::default()
+    };
+    let tests = [
+      (
+        r#"[{"k":"v1.10.0"},{"k":"v2.0.0"},{"k":"1.2.3"},{"k":"v2.0.0-alpha"}]"#,
+        r#"[{"k":"1.2.3"},{"k":"v1.10.0"},{"k":"v2.0.0-alpha"},{"k":"v2.0.0"}]"#,
+      ),
+      (
+        r#"[{"k":"latest"},{"k":"v0.9.0"},{"k":"1.0"}]"#,
+        r#"[{"k":"1.0"},{"k":"latest"},{"k":"v0.9.0"}]"#,
+      ),
+    ];
+    for (input, expected) in tests {
+      let mut actual = parse(input).unwrap();
+      actual.sort_by_value("k", &options);
+      assert_eq!(actual, parse(expected).unwrap(), "input: {}", input);
+    }
+  }
+
+  #[test]
+  fn sort_arrays() {
+    let tests = [
+      ("1", "1"),
+      ("[]", "[]"),
+      (r#"[10,9,"2",-1.5]"#, r#"[-1.5,"2",9,10]"#),
+      (r#"["b","a",null,true]"#, r#"["a","b",null,true]"#),
+      (r#"[3,{"a":[2,1]},1]"#, r#"[3,{"a":[1,2]},1]"#),
+      (r#"[[2,1],[0]]"#, r#"[[1,2],[0]]"#),
+      (r#"{"b":[2,1],"a":1}"#, r#"{"b":[1,2],"a":1}"#),
+      (r#"["1a",10,"9b","9"]"#, r#"["9",10,"1a","9b"]"#),
+    ];
+    for (input, expected) in tests {
+      let mut actual = parse(input).unwrap();
+      actual.sort_arrays();
+      assert_eq!(actual, parse(expected).unwrap(), "input: {}", input);
+    }
+
+    let values = (0..300)
+      .map(|i| match i % 2 {
+        0 => format!("{}", (i * 7919) % 100),
+        _ => format!("\"{}{}\"", (i * 31) % 10, ["a", "b"][i % 4 / 2]),
+      })
+      .collect::<Vec<_>>();
+    let input = format!("[{}]", values.join(","));
+    let mut actual = parse(&input).unwrap();
+    actual.sort_arrays();
+    let Array(xs) = actual else { panic!() };
+    let sorted = xs.iter().map(ToString::to_string).collect::<Vec<_>>();
+    let (numbers, text) = sorted.split_at(150);
+    assert!(numbers.windows(2).all(|w| number(&w[0]) <= number(&w[1])));
+    assert!(text.windows(2).all(|w| w[0] <= w[1]));
+    assert!(text.iter().all(|x| number(x).is_none()));
+  }
+
+  #[test]
+  fn sort_by_value_unescaped() {
+    let tests = [
+      (
+        Array(vec![
+          Object(vec![("\"a\"".into(), Value("\"a b\"".into()))]),
+          Object(vec![("\"a\"".into(), Value("\"a\\nb\"".into()))]),
+        ]),
+        Array(vec![
+          Object(vec![("\"a\"".into(), Value("\"a\\nb\"".into()))]),
+          Object(vec![("\"a\"".into(), Value("\"a b\"".into()))]),
+        ]),
+      ),
+      (
+        Array(vec![
+          Object(vec![("\"a\"".into(), Value("\"a b\"".into()))]),
+          Object(vec![("\"a\"".into(), Value("\"a\\tb\"".into()))]),
+        ]),
+        Array(vec![
+          Object(vec![("\"a\"".into(), Value("\"a\\tb\"".into()))]),
+          Object(vec![("\"a\"".into(), Value("\"a b\"".into()))]),
+        ]),
+      ),
+      (
+        Array(vec![
+          Object(vec![("\"a\"".into(), Value("\"B\"".into()))]),
+          Object(vec![("\"a\"".into(), Value("\"\\u0041\"".into()))]),
+        ]),
+        Array(vec![
+          Object(vec![("\"a\"".into(), Value("\"\\u0041\"".into()))]),
+          Object(vec![("\"a\"".into(), Value("\"B\"".into()))]),
+        ]),
+      ),
+      (
+        Array(vec![
+          Object(vec![("\"a\"".into(), Value("\"a\\nc\"".into()))]),
+          Object(vec![("\"a\"".into(), Value("\"a\nb\"".into()))]),
+        ]),
+        Array(vec![
+          Object(vec![("\"a\"".into(), Value("\"a\nb\"".into()))]),
+          Object(vec![("\"a\"".into(), Value("\"a\\nc\"".into()))]),
+        ]),
+      ),
+    ];
+
+    for (mut actual, expected) in tests {
+      actual.sort_by_value("a", &SortOptions::default());
       assert_eq!(actual, expected);
     }
   }
+
+  #[test]
+  fn unescape_value() {
+    let tests = [
+      ("", ""),
+      ("abc", "abc"),
+      ("a\\nb", "a\nb"),
+      ("a\\tb", "a\tb"),
+      ("\\r\\b\\f", "\r\u{8}\u{c}"),
+      ("\\\"\\\\\\/", "\"\\/"),
+      ("\\u0041", "A"),
+      ("\\u00e9x", "\u{e9}x"),
+      ("\\ud83d\\udd11", "\u{1f511}"),
+      ("\\ud83d", "\u{fffd}"),
+      ("\\u00", "\\u00"),
+      ("\\uzzzz", "\\uzzzz"),
+      ("\\u+041", "\\u+041"),
+      ("\\x", "\\x"),
+      ("a\\", "a\\"),
+    ];
+
+    for (input, expected) in tests {
+      assert_eq!(super::unescape_value(input), expected, "input: `{}`", input);
+    }
+  }
 }
@@ -0,0 +1,599 @@
+use crate::{
+  export::quote_value,
+  node::{
+    Node::{self, Array, Object, Value},
+    OwnedNode,
+  },
+  sort::{unescape_value, unquote},
+};
+use clap::ValueEnum;
+use std::{borrow::Cow, collections::HashSet};
+
+type Entries<'a> = Vec<(Cow<'a, str>, Node<'a>)>;
+
+/// A change of case for `Node::map_keys`.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum KeyCase {
+  Lowercase,
+  Uppercase,
+  #[value(name = "snake_case")]
+  SnakeCase,
+}
+
+impl KeyCase {
+  pub fn apply(self, key: &str) -> String {
+    match self {
+      KeyCase::Lowercase => key.to_lowercase(),
+      KeyCase::Uppercase => key.to_uppercase(),
+      KeyCase::SnakeCase => snake_case(key),
+    }
+  }
+}
+
+/// Lowercases words split at case changes, spaces, `-` and `.`, joining
+/// them with `_`, so `fooBar` and `foo-bar` become `foo_bar`.
+fn snake_case(key: &str) -> String {
+  let chars = key.chars().collect::<Vec<_>>();
+  let mut buf = String::with_capacity(key.len() + 4);
+  for (i, &c) in chars.iter().enumerate() {
+    let prev = i.checked_sub(1).map(|i| chars[i]);
+    let next = chars.get(i + 1).copied();
+    if c.is_uppercase() {
+      // A new word starts after a lower case letter or digit, as in `fooBar`,
+      // or at the last capital of an acronym, as in `HTTPServer`.
+      let after_word = prev.is_some_and(|x| x.is_lowercase() || x.is_ascii_digit());
+      let ends_acronym =
+        prev.is_some_and(char::is_uppercase) && next.is_some_and(char::is_lowercase);
+      if after_word || ends_acronym {
+        buf.push('_');
+      }
+      buf.extend(c.to_lowercase());
+    } else if matches!(c, ' ' | '-' | '.') {
+      buf.push('_');
+    } else {
+      buf.push(c);
+    }
+  }
+  buf
+}
+
+impl<'a> Node<'a> {
+  /// Merges the entries of nested objects into this object, joining the keys
+  /// along the way with `separator`, so `{"a":{"b":1}}` becomes `{"a_b":1}`.
+  /// A flattened key that clashes with another key gets a numeric suffix.
+  /// Arrays and empty objects are kept as values.
+  pub fn flatten_nested_objects_to_top(&mut self, separator: char) {
+    if let Object(xs) = self {
+      let mut entries = vec![];
+      flatten_entries(None, std::mem::take(xs), separator, &mut entries);
+
+      let mut taken = entries
+        .iter()
+        .filter(|(_, _, flattened)| !flattened)
+        .map(|(key, _, _)| unquote(key).to_owned())
+        .collect::<HashSet<_>>();
+
+      *xs = entries
+        .into_iter()
+        .map(|(key, x, flattened)| {
+          if !flattened {
+            return (key, x);
+          }
+          let base = unquote(&key);
+          let mut name = base.to_owned();
+          let mut suffix = 1;
+          while taken.contains(&name) {
+            name = format!("{}{}{}", base, separator, suffix);
+            suffix += 1;
+          }
+          taken.insert(name.clone());
+          (format!("\"{}\"", name).into(), x)
+        })
+        .collect();
+    }
+  }
+
+  /// Splits the keys of this object on `.` and nests their values
+  /// accordingly, so `{"a.b":1,"a.c":2}` becomes `{"a":{"b":1,"c":2}}`.
+  /// A dotted key is kept as it is if its path runs into an existing
+  /// non-object value, e.g. when both `"a"` and `"a.b"` hold values.
+  pub fn expand_dot_notation_keys(&mut self) {
+    if let Object(xs) = self {
+      let values = xs
+        .iter()
+        .filter(|(key, x)| !key.contains('.') && !matches!(x, Object(_)))
+        .map(|(key, _)| unquote(key).to_owned())
+        .collect::<HashSet<_>>();
+
+      let mut entries: Entries = vec![];
+      for (key, x) in std::mem::take(xs) {
+        let path = unquote(&key)
+          .split('.')
+          .map(str::to_owned)
+          .collect::<Vec<_>>();
+
+        if path.len() == 1 || path.iter().any(String::is_empty) || values.contains(&path[0]) {
+          match (entries.iter_mut().find(|(k, _)| *k == key), x) {
+            (Some((_, Object(ys))), Object(zs)) => ys.extend(zs),
+            (_, x) => entries.push((key, x)),
+          }
+        } else if let Err(x) = insert_path(&mut entries, &path, x) {
+          entries.push((key, x));
+        }
+      }
+      *xs = entries;
+    }
+  }
+
+  /// Trims leading and trailing whitespace from the contents of all string
+  /// values. Escaped whitespace such as `\n` is kept.
+  pub fn strip_whitespace_from_values(&mut self) {
+    match self {
+      Value(x) => {
+        if x.starts_with('"') {
+          let trimmed = unquote(x).trim();
+          if trimmed.len() != unquote(x).len() {
+            *x = format!("\"{}\"", trimmed).into();
+          }
+        }
+      }
+      Object(xs) => xs
+        .iter_mut()
+        .for_each(|(_, x)| x.strip_whitespace_from_values()),
+      Array(xs) => xs.iter_mut().for_each(Self::strip_whitespace_from_values),
+    }
+  }
+
+  /// Replaces arrays inside arrays with their elements, down to `depth`
+  /// levels, so `[1,[2,[3]]]` becomes `[1,2,[3]]` with a depth of 1, and
+  /// `[1,2,3]` with `usize::MAX`. Arrays in objects are flattened too.
+  pub fn flatten_array_values(&mut self, depth: usize) {
+    match self {
+      Value(_) => {}
+      Object(xs) => xs
+        .iter_mut()
+        .for_each(|(_, x)| x.flatten_array_values(depth)),
+      Array(xs) => {
+        let mut flat = vec![];
+        flatten_into(std::mem::take(xs), depth, &mut flat);
+        flat
+          .iter_mut()
+          .for_each(|x| flatten_objects_within(x, depth));
+        *xs = flat;
+      }
+    }
+  }
+
+  /// Returns a copy with every object key renamed by `f`, given the
+  /// unquoted and unescaped key, and quoted again afterwards. If keys of an
+  /// object are renamed to the same key, only the last entry is kept.
+  pub fn map_keys(&self, f: &impl Fn(&str) -> String) -> OwnedNode {
+    match self {
+      Value(x) => Value(Cow::Owned(x.to_string())),
+      Object(xs) => {
+        let mut entries: Entries<'static> = vec![];
+        for (key, x) in xs {
+          let key = quote_value(&f(&unescape_value(unquote(key))));
+          entries.retain(|(k, _)| *k != key);
+          entries.push((key.into(), x.map_keys(f)));
+        }
+        Object(entries)
+      }
+      Array(xs) => Array(xs.iter().map(|x| x.map_keys(f)).collect()),
+    }
+  }
+
+  /// Replaces every value with `f` of its raw text, quotes included for
+  /// strings, throughout the tree.
+  pub fn map_values(&self, f: &impl Fn(&str) -> String) -> OwnedNode {
+    match self {
+      Value(x) => Value(Cow::Owned(f(x))),
+      Object(xs) => Object(
+        xs.iter()
+          .map(|(key, x)| (Cow::Owned(key.to_string()), x.map_values(f)))
+          .collect(),
+      ),
+      Array(xs) => Array(xs.iter().map(|x| x.map_values(f)).collect()),
+    }
+  }
+
+  /// Removes the object entries whose unquoted keys `f` returns false for,
+  /// throughout the tree.
+  pub fn retain_keys(&mut self, f: &impl Fn(&str) -> bool) {
+    match self {
+      Value(_) => {}
+      Object(xs) => {
+        xs.retain(|(key, _)| f(unquote(key)));
+        xs.iter_mut().for_each(|(_, x)| x.retain_keys(f));
+      }
+      Array(xs) => xs.iter_mut().for_each(|x| x.retain_keys(f)),
+    }
+  }
+
+  /// Returns a copy of this object with the entries of the object `other`
+  /// merged in. Values of matching keys are replaced by those of `other`,
+  /// unless both are objects, which are merged in turn. Keys only in `other`
+  /// are appended. If either is not an object, a copy of `other` is
+  /// returned.
+  pub fn merge(&self, other: &Node) -> OwnedNode {
+    match (self, other) {
+      (Object(xs), Object(ys)) => {
+        let mut merged = xs
+          .iter()
+          .map(|(key, x)| (Cow::Owned(key.to_string()), x.to_owned()))
+          .collect::<Vec<_>>();
+        for (key, y) in ys {
+          match merged.iter_mut().find(|(k, _)| unquote(k) == unquote(key)) {
+            Some((_, x)) => *x = x.merge(y),
+            None => merged.push((Cow::Owned(key.to_string()), y.to_owned())),
+          }
+        }
+        Object(merged)
+      }
+      _ => other.to_owned(),
+    }
+  }
+
+  /// Appends the elements of `other` to this array. A node that is not an
+  /// array is treated as an array of that single node.
+  pub fn merge_array_concat(&mut self, other: &Node<'a>) {
+    if !matches!(self, Array(_)) {
+      *self = Array(vec![std::mem::replace(self, Array(vec![]))]);
+    }
+    if let Array(xs) = self {
+      match other {
+        Array(ys) => xs.extend(ys.iter().cloned()),
+        y => xs.push(y.clone()),
+      }
+    }
+  }
+}
+
+fn flatten_into<'a>(xs: Vec<Node<'a>>, depth: usize, flat: &mut Vec<Node<'a>>) {
+  for x in xs {
+    match x {
+      Array(ys) if depth > 0 => flatten_into(ys, depth - 1, flat),
+      x => flat.push(x),
+    }
+  }
+}
+
+/// Flattens the arrays of objects in arrays left nested beyond the depth.
+fn flatten_objects_within(node: &mut Node, depth: usize) {
+  match node {
+    Value(_) => {}
+    Object(_) => node.flatten_array_values(depth),
+    Array(xs) => xs.iter_mut().for_each(|x| flatten_objects_within(x, depth)),
+  }
+}
+
+fn insert_path<'a>(
+  entries: &mut Entries<'a>,
+  path: &[String],
+  x: Node<'a>,
+) -> Result<(), Node<'a>> {
+  let (name, rest) = path.split_first().expect("path is not empty");
+  let existing = entries.iter_mut().find(|(key, _)| unquote(key) == name);
+  match (existing, rest.is_empty()) {
+    (None, true) => entries.push((format!("\"{}\"", name).into(), x)),
+    (None, false) => {
+      let mut ys = vec![];
+      insert_path(&mut ys, rest, x)?;
+      entries.push((format!("\"{}\"", name).into(), Object(ys)));
+    }
+    (Some((_, Object(ys))), false) => insert_path(ys, rest, x)?,
+    (Some(_), _) => return Err(x),
+  }
+  Ok(())
+}
+
+fn flatten_entries<'a>(
+  prefix: Option<&str>,
+  xs: Entries<'a>,
+  separator: char,
+  entries: &mut Vec<(Cow<'a, str>, Node<'a>, bool)>,
+) {
+  for (key, x) in xs {
+    let key = match prefix {
+      Some(prefix) => format!("\"{}{}{}\"", prefix, separator, unquote(&key)).into(),
+      None => key,
+    };
+    match x {
+      Object(ys) if !ys.is_empty() => flatten_entries(Some(unquote(&key)), ys, separator, entries),
+      x => entries.push((key, x, prefix.is_some())),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::KeyCase;
+  use crate::parse::parse;
+
+  #[test]
+  fn flatten_nested_objects_to_top() {
+    let tests = vec![
+      ("1", "1"),
+      ("[]", "[]"),
+      ("{}", "{}"),
+      (r#"[{"a":{"b":1}}]"#, r#"[{"a":{"b":1}}]"#),
+      (r#"{"a":1}"#, r#"{"a":1}"#),
+      (r#"{"a":{"b":1,"c":2},"d":3}"#, r#"{"a_b":1,"a_c":2,"d":3}"#),
+      (r#"{"a":{"b":{"c":1}},"d":{}}"#, r#"{"a_b_c":1,"d":{}}"#),
+      (
+        r#"{"a":{"b":[{"c":{"d":1}}]}}"#,
+        r#"{"a_b":[{"c":{"d":1}}]}"#,
+      ),
+      (r#"{"a":{"b":1},"a_b":2}"#, r#"{"a_b_1":1,"a_b":2}"#),
+      (
+        r#"{"a_b":2,"a":{"b":1},"a_b_1":3}"#,
+        r#"{"a_b":2,"a_b_2":1,"a_b_1":3}"#,
+      ),
+      (
+        r#"{"a":{"b_c":1,"b":{"c":2}}}"#,
+        r#"{"a_b_c":1,"a_b_c_1":2}"#,
+      ),
+    ];
+
+    for (input, expected) in tests {
+      let mut actual = parse(input).unwrap();
+      actual.flatten_nested_objects_to_top('_');
+      assert_eq!(actual, parse(expected).unwrap(), "\n input: `{}`\n", input);
+    }
+  }
+
+  #[test]
+  fn expand_dot_notation_keys() {
+    let tests = vec![
+      ("1", "1"),
+      ("{}", "{}"),
+      (r#"[{"a.b":1}]"#, r#"[{"a.b":1}]"#),
+      (r#"{"a":1,"b":{"c.d":2}}"#, r#"{"a":1,"b":{"c.d":2}}"#),
+      (r#"{"a.b":1}"#, r#"{"a":{"b":1}}"#),
+      (r#"{"a.b":1,"a.c":2,"d":3}"#, r#"{"a":{"b":1,"c":2},"d":3}"#),
+      (
+        r#"{"a.b.c":1,"a.b.d":2,"a.e":3}"#,
+        r#"{"a":{"b":{"c":1,"d":2},"e":3}}"#,
+      ),
+      (r#"{"a.b":1,"a":{"c":2}}"#, r#"{"a":{"b":1,"c":2}}"#),
+      (r#"{"a":{"c":2},"a.b":1}"#, r#"{"a":{"c":2,"b":1}}"#),
+      (r#"{"a.b":1,"a":2}"#, r#"{"a.b":1,"a":2}"#),
+      (r#"{"a.b":1,"a.b.c":2}"#, r#"{"a":{"b":1},"a.b.c":2}"#),
+      (r#"{"a.b.c":1,"a.b":2}"#, r#"{"a":{"b":{"c":1}},"a.b":2}"#),
+      (r#"{".a":1,"a.":2,"a..b":3}"#, r#"{".a":1,"a.":2,"a..b":3}"#),
+    ];
+
+    for (input, expected) in tests {
+      let mut actual = parse(input).unwrap();
+      actual.expand_dot_notation_keys();
+      assert_eq!(actual, parse(expected).unwrap(), "\n input: `{}`\n", input);
+    }
+  }
+
+  #[test]
+  fn strip_whitespace_from_values() {
+    let tests = vec![
+      ("1", "1"),
+      ("null", "null"),
+      (r#"" a""#, r#""a""#),
+      (r#""a ""#, r#""a""#),
+      ("\" \ta b\t \"", r#""a b""#),
+      (r#""   ""#, r#""""#),
+      (r#"" \n ""#, r#""\n""#),
+      (
+        r#"{" k ":[" v ",{"x":"y "}]}"#,
+        r#"{" k ":["v",{"x":"y"}]}"#,
+      ),
+    ];
+
+    for (input, expected) in tests {
+      let mut actual = parse(input).unwrap();
+      actual.strip_whitespace_from_values();
+      assert_eq!(actual, parse(expected).unwrap(), "\n input: `{}`\n", input);
+    }
+  }
+
+  #[test]
+  fn flatten_array_values() {
+    let tests = vec![
+      ("1", 1, "1"),
+      ("[]", 1, "[]"),
+      ("[1,[2,3],[4,[5,6]]]", 1, "[1,2,3,4,[5,6]]"),
+      ("[1,[2,3],[4,[5,6]]]", 0, "[1,[2,3],[4,[5,6]]]"),
+      ("[[1,[2,[3,[4]]]]]", 2, "[1,2,[3,[4]]]"),
+      ("[[1,[2,[3,[4]]]]]", usize::MAX, "[1,2,3,4]"),
+      ("[[],[[]],1]", usize::MAX, "[1]"),
+      (
+        r#"{"a":[[1],{"b":[[2],[[3]]]}],"c":{"d":[[true,"x",null]]}}"#,
+        1,
+        r#"{"a":[1,{"b":[2,[3]]}],"c":{"d":[true,"x",null]}}"#,
+      ),
+      (r#"[[[{"a":[[1]]}]]]"#, 1, r#"[[{"a":[1]}]]"#),
+    ];
+
+    for (input, depth, expected) in tests {
+      let mut actual = parse(input).unwrap();
+      actual.flatten_array_values(depth);
+      assert_eq!(
+        actual,
+        parse(expected).unwrap(),
+        "\n input: `{}` depth: {}\n",
+        input,
+        depth
+      );
+    }
+  }
+
+  #[test]
+  fn key_case() {
+    let tests = [
+      (KeyCase::Lowercase, "FooBar", "foobar"),
+      (KeyCase::Uppercase, "foo_bar1", "FOO_BAR1"),
+      (KeyCase::SnakeCase, "fooBar", "foo_bar"),
+      (KeyCase::SnakeCase, "FooBar", "foo_bar"),
+      (KeyCase::SnakeCase, "HTTPServer", "http_server"),
+      (KeyCase::SnakeCase, "userID2x", "user_id2x"),
+      (KeyCase::SnakeCase, "v2Name", "v2_name"),
+      (KeyCase::SnakeCase, "foo-bar baz.qux", "foo_bar_baz_qux"),
+      (KeyCase::SnakeCase, "already_snake", "already_snake"),
+      (KeyCase::SnakeCase, "", ""),
+    ];
+    for (case, key, expected) in tests {
+      assert_eq!(case.apply(key), expected, "{:?}: {}", case, key);
+    }
+  }
+
+  #[test]
+  fn map_values() {
+    let lowercase = |x: &str| match x.starts_with('"') {
+      true => x.to_lowercase(),
+      false => x.to_owned(),
+    };
+    let tests = vec![
+      ("1", "1"),
+      (r#""AB""#, r#""ab""#),
+      (
+        r#"{"B":"X","A":[true,"Y",{"C":"Z"}],"D":1E5}"#,
+        r#"{"B":"x","A":[true,"y",{"C":"z"}],"D":1E5}"#,
+      ),
+      (r#"["C","b","A"]"#, r#"["c","b","a"]"#),
+    ];
+    for (input, expected) in tests {
+      let actual = parse(input).unwrap().map_values(&lowercase);
+      assert_eq!(actual, parse(expected).unwrap(), "\n input: `{}`\n", input);
+    }
+
+    let actual = {
+      let input = String::from(r#"{"a":[1,2]}"#);
+      parse(&input).unwrap().map_values(&|x| format!("{}0", x))
+    };
+    assert_eq!(actual, parse(r#"{"a":[10,20]}"#).unwrap());
+  }
+
+  #[test]
+  fn map_keys() {
+    let tests = vec![
+      (KeyCase::Uppercase, "[1]", "[1]"),
+      (
+        KeyCase::Uppercase,
+        r#"{"a":{"bC":[{"d":"e"}]}}"#,
+        r#"{"A":{"BC":[{"D":"e"}]}}"#,
+      ),
+      (
+        KeyCase::SnakeCase,
+        r#"{"fooBar":1,"x":2,"foo_bar":3}"#,
+        r#"{"x":2,"foo_bar":3}"#,
+      ),
+      (
+        KeyCase::Uppercase,
+        r#"{"a\nb":1,"\u00e9":2,"q\"x":3}"#,
+        r#"{"A\nB":1,"É":2,"Q\"X":3}"#,
+      ),
+      (
+        KeyCase::SnakeCase,
+        r#"{"aB\nc":1,"\u00e9T":2}"#,
+        r#"{"a_b\nc":1,"é_t":2}"#,
+      ),
+    ];
+    for (case, input, expected) in tests {
+      let actual = parse(input).unwrap().map_keys(&|x| case.apply(x));
+      assert_eq!(actual, parse(expected).unwrap(), "\n input: `{}`\n", input);
+    }
+
+    let input = parse(r#"{"userName":{"homeAddress":[{"zipCode":1}]}}"#).unwrap();
+    let snake = input.map_keys(&|x| KeyCase::SnakeCase.apply(x));
+    assert_eq!(snake.map_keys(&|x| KeyCase::SnakeCase.apply(x)), snake);
+    let upper = snake.map_keys(&|x| KeyCase::Uppercase.apply(x));
+    assert_eq!(upper.map_keys(&|x| KeyCase::Lowercase.apply(x)), snake);
+  }
+
+  #[test]
+  fn retain_keys() {
+    let tests = vec![
+      ("1", "1"),
+      (r#"{"a":1,"b":2,"c":3}"#, r#"{"b":2}"#),
+      (
+        r#"{"b":{"a":1,"b":{"b":2,"c":3}},"x":[{"b":4,"d":5},[{"e":6}]]}"#,
+        r#"{"b":{"b":{"b":2}}}"#,
+      ),
+      (
+        r#"[{"a":1},{"b":[{"a":2,"b":3}]}]"#,
+        r#"[{},{"b":[{"b":3}]}]"#,
+      ),
+    ];
+
+    for (input, expected) in tests {
+      let mut actual = parse(input).unwrap();
+      actual.retain_keys(&|key| key == "b");
+      assert_eq!(actual, parse(expected).unwrap(), "\n input: `{}`\n", input);
+    }
+  }
+
+  #[test]
+  fn merge() {
+    let tests = vec![
+      ("{}", "{}", "{}"),
+      (r#"{"a":1}"#, "[2]", "[2]"),
+      ("1", r#"{"a":1}"#, r#"{"a":1}"#),
+      (
+        r#"{"b":1,"a":2}"#,
+        r#"{"c":3,"b":4}"#,
+        r#"{"b":4,"a":2,"c":3}"#,
+      ),
+      (
+        r#"{"a":{"x":1,"y":[1]},"b":{"z":1}}"#,
+        r#"{"a":{"y":[2],"w":{"v":1}},"b":null}"#,
+        r#"{"a":{"x":1,"y":[2],"w":{"v":1}},"b":null}"#,
+      ),
+    ];
+
+    for (input, other, expected) in tests {
+      let actual = parse(input).unwrap().merge(&parse(other).unwrap());
+      assert_eq!(
+        actual,
+        parse(expected).unwrap(),
+        "\n input: `{}` other: `{}`\n",
+        input,
+        other
+      );
+    }
+
+    let other = r#"{"a":1}"#.to_owned();
+    let merged = parse("{}").unwrap().merge(&parse(&other).unwrap());
+    drop(other);
+    assert_eq!(merged.to_compact_string(), r#"{"a":1}"#);
+  }
+
+  #[test]
+  fn merge_array_concat() {
+    let tests = vec![
+      ("[1,2]", "[3,4]", "[1,2,3,4]"),
+      ("[]", "[1]", "[1]"),
+      ("[1]", "[]", "[1]"),
+      (r#"{"a":1}"#, "[2]", r#"[{"a":1},2]"#),
+      ("[1]", r#"{"a":2}"#, r#"[1,{"a":2}]"#),
+      ("1", "2", "[1,2]"),
+    ];
+
+    for (input, other, expected) in tests {
+      let mut actual = parse(input).unwrap();
+      actual.merge_array_concat(&parse(other).unwrap());
+      assert_eq!(
+        actual,
+        parse(expected).unwrap(),
+        "\n input: `{}` `{}`\n",
+        input,
+        other
+      );
+    }
+  }
+
+  #[test]
+  fn expand_dot_notation_keys_reverses_flatten() {
+    let input = r#"{"a":{"b":1,"c":{"d":[2]}},"e":3}"#;
+    let mut actual = parse(input).unwrap();
+    actual.flatten_nested_objects_to_top('.');
+    actual.expand_dot_notation_keys();
+    assert_eq!(actual, parse(input).unwrap());
+  }
+}
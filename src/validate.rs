@@ -0,0 +1,317 @@
+use crate::{
+  node::{
+    pointer_token,
+    Node::{self, Array, Object, Value},
+  },
+  sort::unquote,
+};
+use clap::ValueEnum;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub struct ValidationError {
+  /// JSON Pointer to the offending location.
+  pub path: String,
+  pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}: {}", self.path, self.message)
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum NamingConvention {
+  #[value(name = "camel")]
+  CamelCase,
+  #[value(name = "snake")]
+  SnakeCase,
+  #[value(name = "kebab")]
+  KebabCase,
+  #[value(name = "screaming")]
+  ScreamingSnake,
+}
+
+impl fmt::Display for NamingConvention {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
+      NamingConvention::CamelCase => "camelCase",
+      NamingConvention::SnakeCase => "snake_case",
+      NamingConvention::KebabCase => "kebab-case",
+      NamingConvention::ScreamingSnake => "SCREAMING_SNAKE",
+    })
+  }
+}
+
+impl NamingConvention {
+  pub fn matches(self, key: &str) -> bool {
+    match self {
+      NamingConvention::CamelCase => {
+        key.starts_with(|x: char| x.is_ascii_lowercase())
+          && key.chars().all(|x| x.is_ascii_alphanumeric())
+      }
+      NamingConvention::SnakeCase => {
+        is_delimited(key, '_', |x| x.is_ascii_lowercase() || x.is_ascii_digit())
+      }
+      NamingConvention::KebabCase => {
+        is_delimited(key, '-', |x| x.is_ascii_lowercase() || x.is_ascii_digit())
+      }
+      NamingConvention::ScreamingSnake => {
+        is_delimited(key, '_', |x| x.is_ascii_uppercase() || x.is_ascii_digit())
+      }
+    }
+  }
+}
+
+/// Returns whether `key` is non-empty words of `is_word_char` joined by single
+/// `separator`s, starting with a letter.
+fn is_delimited(key: &str, separator: char, is_word_char: impl Fn(char) -> bool) -> bool {
+  key.starts_with(|x: char| x.is_ascii_alphabetic())
+    && key
+      .split(separator)
+      .all(|word| !word.is_empty() && word.chars().all(&is_word_char))
+}
+
+impl Node<'_> {
+  /// Reports every object key in the tree that does not follow `convention`.
+  pub fn validate_key_naming_convention(
+    &self,
+    convention: NamingConvention,
+  ) -> Vec<ValidationError> {
+    let mut errors = vec![];
+    validate_keys(self, convention, "", &mut errors);
+    errors
+  }
+}
+
+impl Node<'_> {
+  /// Returns the JSON Pointers of string values that escape the same
+  /// character both ways, e.g. as `\"` and as `\u0022`.
+  pub fn detect_encoding_inconsistencies(&self) -> Vec<String> {
+    let mut paths = vec![];
+    self.walk(&mut |path, node| {
+      if let Value(x) = node {
+        if x.starts_with('"') && has_mixed_escapes(unquote(x)) {
+          paths.push(path.to_owned());
+        }
+      }
+    });
+    paths
+  }
+}
+
+impl Node<'_> {
+  /// Reports numbers that an `f64` cannot hold: ones out of range, and ones
+  /// with more than 15 significant digits, which may lose precision.
+  pub fn validate_no_number_overflow(&self) -> Vec<ValidationError> {
+    let mut errors = vec![];
+    self.walk(&mut |path, node| {
+      let Value(x) = node else {
+        return;
+      };
+      let Ok(n) = x.parse::<f64>() else {
+        return;
+      };
+      let digits = significant_digits(x);
+      let message = if n.is_infinite() {
+        "is out of range for f64"
+      } else if n == 0.0 && digits > 0 {
+        "is too small for f64"
+      } else if digits > 15 {
+        "has more than 15 significant digits"
+      } else {
+        return;
+      };
+      errors.push(ValidationError {
+        path: path.to_owned(),
+        message: format!("number {} {}", x, message),
+      });
+    });
+    errors
+  }
+}
+
+/// Counts the digits of a number's mantissa from the first to the last
+/// non-zero digit.
+fn significant_digits(number: &str) -> usize {
+  let mantissa = number.split(['e', 'E']).next().unwrap_or("");
+  let digits = mantissa
+    .chars()
+    .filter(char::is_ascii_digit)
+    .collect::<String>();
+  digits.trim_matches('0').len()
+}
+
+fn has_mixed_escapes(s: &str) -> bool {
+  let mut short = vec![];
+  let mut unicode = vec![];
+  let mut chars = s.chars();
+  while let Some(c) = chars.next() {
+    if c != '\\' {
+      continue;
+    }
+    match chars.next() {
+      Some('u') => {
+        let hex = chars.by_ref().take(4).collect::<String>();
+        if let Some(x) = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+          unicode.push(x);
+        }
+      }
+      Some('b') => short.push('\u{8}'),
+      Some('f') => short.push('\u{c}'),
+      Some('n') => short.push('\n'),
+      Some('r') => short.push('\r'),
+      Some('t') => short.push('\t'),
+      Some(x) => short.push(x),
+      None => {}
+    }
+  }
+  short.iter().any(|x| unicode.contains(x))
+}
+
+fn validate_keys(
+  node: &Node,
+  convention: NamingConvention,
+  path: &str,
+  errors: &mut Vec<ValidationError>,
+) {
+  match node {
+    Value(_) => {}
+    Object(xs) => xs.iter().for_each(|(key, x)| {
+      let key = unquote(key);
+      let path = format!("{}/{}", path, pointer_token(key));
+      if !convention.matches(key) {
+        errors.push(ValidationError {
+          path: path.clone(),
+          message: format!("key `{}` is not {}", key, convention),
+        });
+      }
+      validate_keys(x, convention, &path, errors);
+    }),
+    Array(xs) => xs.iter().enumerate().for_each(|(i, x)| {
+      validate_keys(x, convention, &format!("{}/{}", path, i), errors);
+    }),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::NamingConvention::{self, *};
+  use crate::parse::parse;
+
+  #[test]
+  fn matches() {
+    let tests: Vec<(NamingConvention, Vec<&str>, Vec<&str>)> = vec![
+      (
+        CamelCase,
+        vec!["a", "abc", "aBc", "userId", "user2Name"],
+        vec!["", "Abc", "a_b", "a-b", "a b", "1a", "userID!"],
+      ),
+      (
+        SnakeCase,
+        vec!["a", "abc", "a_b", "user_id", "user2_name"],
+        vec!["", "A", "aB", "a__b", "_a", "a_", "a-b", "1a", "USER_ID"],
+      ),
+      (
+        KebabCase,
+        vec!["a", "abc", "a-b", "user-id", "user2-name"],
+        vec!["", "A", "aB", "a--b", "-a", "a-", "a_b", "1a"],
+      ),
+      (
+        ScreamingSnake,
+        vec!["A", "ABC", "A_B", "USER_ID", "USER2_NAME"],
+        vec!["", "a", "Ab", "A__B", "_A", "A_", "A-B", "1A", "user_id"],
+      ),
+    ];
+
+    for (convention, good, bad) in tests {
+      for key in good {
+        assert!(convention.matches(key), "{:?} `{}`", convention, key);
+      }
+      for key in bad {
+        assert!(!convention.matches(key), "{:?} `{}`", convention, key);
+      }
+    }
+  }
+
+  #[test]
+  fn validate_key_naming_convention() {
+    let tests = vec![
+      (SnakeCase, "1", vec![]),
+      (SnakeCase, r#"{"a_b":1,"c":[{"d_e":{}}]}"#, vec![]),
+      (
+        SnakeCase,
+        r#"{"aB":1,"c":[{"d":{"E/f":{}}}]}"#,
+        vec![
+          "/aB: key `aB` is not snake_case",
+          "/c/0/d/E~1f: key `E/f` is not snake_case",
+        ],
+      ),
+      (
+        CamelCase,
+        r#"[{"a_b":{"cD":1,"e~":2}}]"#,
+        vec![
+          "/0/a_b: key `a_b` is not camelCase",
+          "/0/a_b/e~0: key `e~` is not camelCase",
+        ],
+      ),
+    ];
+
+    for (convention, input, expected) in tests {
+      let actual = parse(input)
+        .unwrap()
+        .validate_key_naming_convention(convention)
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>();
+      assert_eq!(actual, expected, "\n input: `{}`\n", input);
+    }
+  }
+
+  #[test]
+  fn detect_encoding_inconsistencies() {
+    let tests = vec![
+      ("1", vec![]),
+      (r#""\"\u0022""#, vec![""]),
+      (
+        r#"{"a":"\"x\"","b":["\u0022","\"\u0022"],"c/d":{"e":"\n\u000A"}}"#,
+        vec!["/b/1", "/c~1d/e"],
+      ),
+      (r#"["\"\\u0022","\"\u0027","\\\u005c"]"#, vec!["/2"]),
+    ];
+    for (input, expected) in tests {
+      assert_eq!(
+        parse(input).unwrap().detect_encoding_inconsistencies(),
+        expected,
+        "input: {}",
+        input
+      );
+    }
+  }
+
+  #[test]
+  fn validate_no_number_overflow() {
+    let tests = vec![
+      (
+        r#"[1, -2.5, 0, 0.0, 1e308, 123456789012345, 1000000000000000000000, "1e999", true]"#,
+        vec![],
+      ),
+      (
+        r#"{"a":[1e309,-1e309],"b":{"c":1234567890123456,"d":0.1234567890123456},"e":1e-400}"#,
+        vec![
+          "/a/0: number 1e309 is out of range for f64",
+          "/a/1: number -1e309 is out of range for f64",
+          "/b/c: number 1234567890123456 has more than 15 significant digits",
+          "/b/d: number 0.1234567890123456 has more than 15 significant digits",
+          "/e: number 1e-400 is too small for f64",
+        ],
+      ),
+    ];
+    for (input, expected) in tests {
+      let actual = parse(input).unwrap().validate_no_number_overflow();
+      let actual = actual.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+      assert_eq!(actual, expected, "input: {}", input);
+    }
+  }
+}